@@ -0,0 +1,106 @@
+use ratatui::prelude::*;
+
+/// One layer of the stacked area chart, a value in ms per frame.
+pub struct Band {
+    pub name: String,
+    pub color: Color,
+    pub values: Vec<f64>,
+}
+
+/// Stacked area chart of per-frame values, drawn directly into the buffer
+/// since the ratatui `Chart` can't stack datasets.
+pub struct StackedAreas<'a> {
+    pub bands: &'a [Band],
+    /// frame index range `[start, end)` to draw
+    pub range: (usize, usize),
+    /// value (in ms) at the top of the chart
+    pub max: f64,
+    /// color of the "ms" title
+    pub title_color: Color,
+    /// x axis labels for the start and end of the range
+    pub labels: (String, String),
+}
+
+const Y_LABEL_WIDTH: u16 = 6;
+
+impl Widget for StackedAreas<'_> {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        if area.height < 3 || area.width <= Y_LABEL_WIDTH {
+            return;
+        }
+
+        // title line with the legend
+        let mut x = area.x;
+        buf.set_string(
+            x,
+            area.y,
+            "ms (linear)",
+            Style::default().fg(self.title_color),
+        );
+        x += 12;
+        for band in self.bands {
+            if x >= area.right() {
+                break;
+            }
+            let (end_x, _) = buf.set_stringn(
+                x,
+                area.y,
+                format!("■ {} ", band.name),
+                (area.right() - x) as usize,
+                Style::default().fg(band.color),
+            );
+            x = end_x;
+        }
+
+        let plot = Rect {
+            x: area.x + Y_LABEL_WIDTH,
+            y: area.y + 1,
+            width: area.width - Y_LABEL_WIDTH,
+            height: area.height - 2,
+        };
+        buf.set_string(area.x, plot.y, format!("{:.1}", self.max), Style::default());
+        buf.set_string(area.x, plot.bottom() - 1, "0.0", Style::default());
+        buf.set_string(plot.x, plot.bottom(), &self.labels.0, Style::default());
+        let end_label = &self.labels.1;
+        buf.set_string(
+            plot.right()
+                .saturating_sub(end_label.chars().count() as u16),
+            plot.bottom(),
+            end_label,
+            Style::default(),
+        );
+
+        let (start, end) = self.range;
+        if end <= start || self.max <= 0.0 {
+            return;
+        }
+        let frames = end - start;
+        let row_ms = self.max / plot.height as f64;
+        let mut stack = vec![0.0; self.bands.len()];
+        for col in 0..plot.width {
+            // average over all frames falling into this column
+            let lo = start + frames * col as usize / plot.width as usize;
+            let hi = (start + frames * (col as usize + 1) / plot.width as usize).max(lo + 1);
+            let mut total = 0.0;
+            for (i, band) in self.bands.iter().enumerate() {
+                let values = &band.values[lo.min(band.values.len())..hi.min(band.values.len())];
+                let avg = if values.is_empty() {
+                    0.0
+                } else {
+                    values.iter().sum::<f64>() / values.len() as f64
+                };
+                total += avg;
+                stack[i] = total;
+            }
+
+            for row in 0..plot.height {
+                let center = (row as f64 + 0.5) * row_ms;
+                if let Some(band) = stack.iter().position(|top| center < *top) {
+                    buf[(plot.x + col, plot.bottom() - 1 - row)]
+                        .set_symbol("█")
+                        .set_fg(self.bands[band].color);
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,741 @@
+use std::fmt;
+use std::time::Duration;
+
+use crate::export;
+use crate::format::Radix;
+use crate::help::COMMANDS;
+use crate::trace::parse_duration;
+use crate::{ChildOrder, DetailMode, Scale, View};
+
+/// A parsed `:` command.
+#[derive(Debug, PartialEq)]
+pub enum Command {
+    Quit,
+    /// charts all frames again
+    FrameAll,
+    /// charts the frames with ids `start..end`
+    FrameRange {
+        start: usize,
+        end: usize,
+    },
+    Inspect(InspectTarget),
+    /// inspects a frame and centers the chart on it
+    FrameGoto(u64),
+    /// compares the children of two frames
+    FrameDiff(u64, u64),
+    /// leaves the frames with ids `lower..=upper` out
+    Exclude {
+        lower: u64,
+        upper: u64,
+    },
+    ExcludeOff,
+    /// charts only frames taking `min..=max` ms, no `max` is open-ended
+    Filter {
+        min: f64,
+        max: Option<f64>,
+    },
+    FilterClear,
+    Mark {
+        frame_id: u64,
+        note: String,
+    },
+    /// rolling budget compliance over a window of frames, `None` removes it
+    Compliance(Option<usize>),
+    Fps,
+    /// toggles the grid lines of the chart
+    Grid,
+    /// trailing moving average over a window of frames, `None` removes it
+    Average(Option<usize>),
+    /// saves the session, to the `--session` file without a path
+    Write(Option<String>),
+    LayoutSave(String),
+    LayoutLoad(String),
+    ExportThumb {
+        path: String,
+        width: u32,
+        height: u32,
+    },
+    ExportCsv(String),
+    /// frames and children in the Chrome trace event format
+    ExportChrome(String),
+    /// folded stacks of the span tree, for flamegraphs
+    ExportFolded(String),
+    ExportStats(String),
+    Screenshot {
+        path: String,
+        ansi: bool,
+    },
+    ZoomToSelection,
+    Regressions,
+    Stats,
+    /// lists and marks the missing frame ids
+    Gaps,
+    GapsOff,
+    Reload,
+    AggTarget,
+    /// lists the slowest frames, optionally a number other than the default
+    Top(Option<usize>),
+    /// histogram view, optionally with a new number of bins
+    Histogram(Option<usize>),
+    Sort(ChildOrder),
+    Search(String),
+    /// compares `new` against `base`, or the loaded frames without one
+    CompareRuns {
+        base: Option<String>,
+        new: String,
+    },
+    /// charts every k frames as one, `None` charts every frame again
+    Merge(Option<usize>),
+    GotoMostChildren,
+    FindMessage(String),
+    /// removes the highlight of `:find message`, `:idle-warn` and `:spikes`
+    ClearHighlight,
+    FirstOver(Duration),
+    Spikes(f64),
+    IdleWarn(f64),
+    Radix(Radix),
+    Precision(usize),
+    Timeline,
+    Yank,
+    /// uppercase level to filter by, `None` shows all levels
+    Level(Option<String>),
+    View(View),
+    DetailMode(DetailMode),
+    Scale(Scale),
+}
+
+/// Which frame `:f inspect` inspects.
+#[derive(Debug, PartialEq)]
+pub enum InspectTarget {
+    /// the longest frame, the first one if several are equally long
+    Max,
+    Min,
+    Id(u64),
+}
+
+/// Why a command couldn't be parsed, shown in the command area.
+#[derive(Debug, PartialEq)]
+pub struct CmdError(pub String);
+
+impl fmt::Display for CmdError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+fn usage(usage: &str) -> CmdError {
+    CmdError(format!("usage: {}", usage))
+}
+
+fn frame_id(s: &str) -> Result<u64, CmdError> {
+    s.parse()
+        .map_err(|_| CmdError(format!("invalid frame id '{}'", s)))
+}
+
+/// Parses `a..b` into its bounds.
+fn range<T: std::str::FromStr>(s: &str) -> Option<(T, T)> {
+    let (lower, upper) = s.split_once("..")?;
+    Some((lower.parse().ok()?, upper.parse().ok()?))
+}
+
+/// A duration range in ms like `8..16.6`, or `16.6..` without an upper end.
+fn millis_range(s: &str) -> Option<(f64, Option<f64>)> {
+    let (lower, upper) = s.split_once("..")?;
+    let min: f64 = lower.parse().ok().filter(|min: &f64| *min >= 0.0)?;
+    let max = match upper {
+        "" => None,
+        upper => Some(upper.parse().ok().filter(|max: &f64| *max >= min)?),
+    };
+    Some((min, max))
+}
+
+/// Parses a command as typed after `:`, including the `:`.
+pub fn parse_command(input: &str) -> Result<Command, CmdError> {
+    let mut iter = input.split_whitespace();
+    let command = match iter.next() {
+        Some(":q") => Command::Quit,
+        Some(":f") => match iter.next() {
+            Some("all") => Command::FrameAll,
+            Some("inspect") => Command::Inspect(match iter.next() {
+                Some("max") => InspectTarget::Max,
+                Some("min") => InspectTarget::Min,
+                Some(id) => InspectTarget::Id(frame_id(id)?),
+                None => return Err(usage(":f inspect <max|min|id>")),
+            }),
+            Some("goto") => match iter.next() {
+                Some(id) => Command::FrameGoto(frame_id(id)?),
+                None => return Err(usage(":f goto <id>")),
+            },
+            Some("diff") => match (iter.next(), iter.next()) {
+                (Some(first), Some(second)) => {
+                    Command::FrameDiff(frame_id(first)?, frame_id(second)?)
+                }
+                _ => return Err(usage(":f diff <id1> <id2>")),
+            },
+            Some(s) => match range(s) {
+                Some((start, end)) => Command::FrameRange { start, end },
+                None => return Err(CmdError(format!("invalid frame range '{}'", s))),
+            },
+            None => return Err(usage(":f <a..b|all|inspect|goto|diff>")),
+        },
+        Some(":exclude") => match iter.next() {
+            Some("off") => Command::ExcludeOff,
+            Some(s) => match range(s) {
+                Some((lower, upper)) if lower <= upper => Command::Exclude { lower, upper },
+                _ => return Err(CmdError(format!("invalid frame range '{}'", s))),
+            },
+            None => return Err(usage(":exclude a..b|off")),
+        },
+        Some(":filter") => match iter.next() {
+            Some("clear") => Command::FilterClear,
+            Some(s) => match millis_range(s) {
+                Some((min, max)) => Command::Filter { min, max },
+                None => return Err(CmdError(format!("invalid duration range '{}'", s))),
+            },
+            None => return Err(usage(":filter <min_ms>..[max_ms]|clear")),
+        },
+        // bookmark notes, :mark <id> "note"
+        Some(":mark") => match iter.next().map(str::parse::<u64>) {
+            Some(Ok(frame_id)) => Command::Mark {
+                frame_id,
+                note: input
+                    .splitn(3, ' ')
+                    .nth(2)
+                    .unwrap_or("")
+                    .trim()
+                    .trim_matches('"')
+                    .to_string(),
+            },
+            _ => return Err(usage(":mark <id> \"note\"")),
+        },
+        Some(":compliance") => Command::Compliance(match iter.next() {
+            Some("off") => None,
+            Some(window) => match window.parse::<usize>() {
+                Ok(window) => Some(window.max(1)),
+                Err(_) => return Err(usage(":compliance [window|off]")),
+            },
+            None => Some(crate::DEFAULT_COMPLIANCE_WINDOW),
+        }),
+        Some(":fps") => Command::Fps,
+        Some(":grid") => Command::Grid,
+        // 0 or nothing removes the average
+        Some(":avg") => match iter.next().map(str::parse::<usize>) {
+            Some(Ok(0)) | None => Command::Average(None),
+            Some(Ok(window)) => Command::Average(Some(window)),
+            Some(Err(_)) => return Err(usage(":avg <window>")),
+        },
+        Some(":w") => Command::Write(iter.next().map(str::to_string)),
+        Some(":layout") => match (iter.next(), iter.next()) {
+            (Some("save"), Some(name)) => Command::LayoutSave(name.to_string()),
+            (Some("load"), Some(name)) => Command::LayoutLoad(name.to_string()),
+            _ => return Err(usage(":layout <save|load> <name>")),
+        },
+        Some(":export") => match (iter.next(), iter.next()) {
+            (Some("thumb"), Some(path)) => {
+                let size = match iter.next() {
+                    Some(size) => export::parse_size(size),
+                    None => Some(export::DEFAULT_THUMBNAIL_SIZE),
+                };
+                match size {
+                    Some((width, height)) => Command::ExportThumb {
+                        path: path.to_string(),
+                        width,
+                        height,
+                    },
+                    None => return Err(CmdError("invalid size, expected WxH".to_string())),
+                }
+            }
+            (Some("csv"), Some(path)) => Command::ExportCsv(path.to_string()),
+            (Some("chrome"), Some(path)) => Command::ExportChrome(path.to_string()),
+            (Some("folded"), Some(path)) => Command::ExportFolded(path.to_string()),
+            (Some("stats"), Some(path)) => Command::ExportStats(path.to_string()),
+            _ => {
+                return Err(usage(
+                    ":export <thumb <path> [WxH]|csv <path>|chrome <path>|folded <path>|stats <path>>",
+                ));
+            }
+        },
+        Some(":screenshot") => match iter.next() {
+            Some(path) => Command::Screenshot {
+                path: path.to_string(),
+                ansi: iter.next() != Some("plain"),
+            },
+            None => return Err(usage(":screenshot <path> [plain]")),
+        },
+        Some(":zoom-to-selection") => Command::ZoomToSelection,
+        Some(":regressions") => Command::Regressions,
+        Some(":stats") => Command::Stats,
+        Some(":gaps") => match iter.next() {
+            None => Command::Gaps,
+            Some("off") => Command::GapsOff,
+            Some(_) => return Err(usage(":gaps [off]")),
+        },
+        Some(":r") => Command::Reload,
+        Some(":agg") => match iter.next() {
+            Some("target") => Command::AggTarget,
+            _ => return Err(usage(":agg target")),
+        },
+        Some(":top") => match iter.next().map(str::parse::<usize>) {
+            Some(Ok(n)) if n > 0 => Command::Top(Some(n)),
+            None => Command::Top(None),
+            _ => return Err(usage(":top [n]")),
+        },
+        Some(":hist") => match iter.next().map(str::parse::<usize>) {
+            Some(Ok(bins)) if bins > 0 => Command::Histogram(Some(bins)),
+            None => Command::Histogram(None),
+            _ => return Err(usage(":hist [bins]")),
+        },
+        // order of the children of the inspected frame
+        Some(":sort") => Command::Sort(match iter.next() {
+            Some("dur") => ChildOrder::Duration,
+            Some("name") => ChildOrder::Name,
+            Some("orig") => ChildOrder::Original,
+            _ => return Err(usage(":sort <dur|name|orig>")),
+        }),
+        Some(":search") => {
+            let text = iter.collect::<Vec<_>>().join(" ");
+            if text.is_empty() {
+                return Err(usage(":search <text>"));
+            }
+            Command::Search(text)
+        }
+        Some(":compare-runs") => match (iter.next(), iter.next()) {
+            (Some(base), Some(new)) => Command::CompareRuns {
+                base: Some(base.to_string()),
+                new: new.to_string(),
+            },
+            (Some(new), None) => Command::CompareRuns {
+                base: None,
+                new: new.to_string(),
+            },
+            _ => return Err(usage(":compare-runs [<base file>] <new file>")),
+        },
+        Some(":merge") => match iter.next() {
+            Some("off") => Command::Merge(None),
+            Some(k) => match k.parse::<usize>() {
+                Ok(k) if k >= 1 => Command::Merge(Some(k)),
+                _ => return Err(CmdError(format!("invalid merge size '{}'", k))),
+            },
+            None => return Err(usage(":merge <frames|off>")),
+        },
+        Some(":goto") => match iter.next() {
+            Some("most-children") => Command::GotoMostChildren,
+            _ => return Err(usage(":goto most-children")),
+        },
+        Some(":find") => match iter.next() {
+            Some("message") => {
+                let text = iter.collect::<Vec<_>>().join(" ");
+                if text.is_empty() {
+                    Command::ClearHighlight
+                } else {
+                    Command::FindMessage(text)
+                }
+            }
+            _ => return Err(usage(":find message <text>")),
+        },
+        Some(":first-over") => match iter.next().map(parse_duration) {
+            Some(Ok(threshold)) => Command::FirstOver(threshold),
+            Some(Err(e)) => return Err(CmdError(e.to_string())),
+            None => return Err(usage(":first-over <duration>")),
+        },
+        Some(":spikes") => match iter.next().map(str::parse::<f64>) {
+            Some(Ok(k)) if k > 0.0 => Command::Spikes(k),
+            Some(_) => return Err(usage(":spikes [k]")),
+            None => Command::Spikes(crate::DEFAULT_SPIKE_STDDEVS),
+        },
+        Some(":idle-warn") => match iter.next() {
+            Some("off") => Command::ClearHighlight,
+            Some(fraction) => match fraction.parse::<f64>() {
+                Ok(fraction) => Command::IdleWarn(fraction),
+                Err(_) => return Err(CmdError(format!("invalid idle fraction '{}'", fraction))),
+            },
+            None => Command::IdleWarn(crate::DEFAULT_IDLE_WARN),
+        },
+        Some(":radix") => Command::Radix(match iter.next() {
+            Some("dec") => Radix::Dec,
+            Some("hex") => Radix::Hex,
+            _ => return Err(usage(":radix <dec|hex>")),
+        }),
+        Some(":precision") => match iter.next().map(str::parse::<usize>) {
+            Some(Ok(precision)) if precision <= 9 => Command::Precision(precision),
+            _ => return Err(usage(":precision <0-9>")),
+        },
+        Some(":timeline") => Command::Timeline,
+        Some(":cdf") => Command::View(View::Cdf),
+        Some(":stacked") => Command::View(View::Stacked),
+        Some(":yank") => Command::Yank,
+        Some(":level") => match iter.next().map(str::to_ascii_uppercase).as_deref() {
+            Some("ALL") => Command::Level(None),
+            Some(level @ ("TRACE" | "DEBUG" | "INFO" | "WARN" | "ERROR")) => {
+                Command::Level(Some(level.to_string()))
+            }
+            _ => return Err(usage(":level <trace|debug|info|warn|error|all>")),
+        },
+        Some(":view") => match iter.next() {
+            Some("frames") => Command::View(View::Frames),
+            Some("interval") => Command::View(View::Interval),
+            Some("areas") => Command::View(View::Areas),
+            Some("histogram") => Command::View(View::Histogram),
+            Some("cdf") => Command::View(View::Cdf),
+            Some("stacked") => Command::View(View::Stacked),
+            Some("list") => Command::DetailMode(DetailMode::List),
+            Some("table") => Command::DetailMode(DetailMode::Table),
+            Some("donut") => Command::DetailMode(DetailMode::Donut),
+            Some("timeline") => Command::DetailMode(DetailMode::Timeline),
+            _ => {
+                return Err(usage(
+                    ":view <frames|interval|areas|histogram|cdf|stacked|list|table|donut|timeline>",
+                ));
+            }
+        },
+        // y-axis scale
+        Some(":y") => Command::Scale(match iter.next() {
+            Some("log10") => Scale::Log10,
+            Some("log2") => Scale::Log2,
+            Some("linear") => Scale::Linear,
+            Some("dual") => Scale::Dual,
+            _ => return Err(usage(":y <log10|log2|linear|dual>")),
+        }),
+        Some(":scale") => Command::Scale(match iter.next() {
+            Some("log") => Scale::Log10,
+            Some("linear") => Scale::Linear,
+            _ => return Err(usage(":scale <log|linear>")),
+        }),
+        Some(command) => return Err(CmdError(format!("unknown command '{}'", command))),
+        None => return Err(CmdError("empty command".to_string())),
+    };
+    Ok(command)
+}
+
+/// Completions of the last word of `input` from the commands listed in the
+/// help, as whole command lines in the order of the help.
+pub fn completions(input: &str) -> Vec<String> {
+    let mut words: Vec<&str> = input.split_whitespace().collect();
+    let current = match input.ends_with(char::is_whitespace) || words.is_empty() {
+        true => "",
+        false => words.pop().unwrap_or_default(),
+    };
+    let prefix = &input[..input.len() - current.len()];
+    let mut candidates: Vec<String> = Vec::new();
+    for (usage, _) in COMMANDS {
+        let usage: Vec<&str> = usage.split_whitespace().collect();
+        let Some(word) = usage.get(words.len()) else {
+            continue;
+        };
+        // placeholders match any word typed before the current one
+        let typed_matches = words.iter().zip(&usage).all(|(typed, word)| {
+            let literals = literal_words(word);
+            literals.is_empty() || literals.contains(typed)
+        });
+        if !typed_matches {
+            continue;
+        }
+        for literal in literal_words(word) {
+            let candidate = format!("{}{}", prefix, literal);
+            if literal.starts_with(current) && !candidates.contains(&candidate) {
+                candidates.push(candidate);
+            }
+        }
+    }
+    candidates
+}
+
+/// The words that can be typed literally for a word of a usage, `<a|b>`
+/// lists alternatives, other bracketed words are placeholders.
+fn literal_words(word: &str) -> Vec<&str> {
+    let word = match word.strip_prefix('<').and_then(|w| w.strip_suffix('>')) {
+        Some(inner) if inner.contains('|') && !inner.contains(['<', '>']) => inner,
+        _ => word,
+    };
+    word.split('|')
+        .filter(|w| {
+            !w.is_empty()
+                && w.chars()
+                    .all(|c| c.is_alphanumeric() || c == '-' || c == ':')
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(input: &str) -> Command {
+        parse_command(input).unwrap()
+    }
+
+    fn error(input: &str) -> String {
+        parse_command(input).unwrap_err().0
+    }
+
+    #[test]
+    fn test_frame_commands() {
+        assert_eq!(parse(":q"), Command::Quit);
+        assert_eq!(parse(":f all"), Command::FrameAll);
+        assert_eq!(
+            parse(":f 10..20"),
+            Command::FrameRange { start: 10, end: 20 }
+        );
+        assert_eq!(error(":f 10..x"), "invalid frame range '10..x'");
+        assert_eq!(error(":f 10"), "invalid frame range '10'");
+        assert!(error(":f").starts_with("usage:"));
+
+        assert_eq!(
+            parse(":f inspect max"),
+            Command::Inspect(InspectTarget::Max)
+        );
+        assert_eq!(
+            parse(":f inspect min"),
+            Command::Inspect(InspectTarget::Min)
+        );
+        assert_eq!(
+            parse(":f inspect 7"),
+            Command::Inspect(InspectTarget::Id(7))
+        );
+        assert_eq!(error(":f inspect seven"), "invalid frame id 'seven'");
+        assert!(error(":f inspect").starts_with("usage:"));
+
+        assert_eq!(parse(":f goto 3"), Command::FrameGoto(3));
+        assert!(error(":f goto").starts_with("usage:"));
+        assert_eq!(parse(":f diff 1 2"), Command::FrameDiff(1, 2));
+        assert_eq!(error(":f diff 1 -2"), "invalid frame id '-2'");
+        assert!(error(":f diff 1").starts_with("usage:"));
+    }
+
+    #[test]
+    fn test_exclude_and_mark() {
+        assert_eq!(
+            parse(":exclude 5..9"),
+            Command::Exclude { lower: 5, upper: 9 }
+        );
+        assert_eq!(parse(":exclude off"), Command::ExcludeOff);
+        assert_eq!(error(":exclude 9..5"), "invalid frame range '9..5'");
+        assert!(error(":exclude").starts_with("usage:"));
+
+        assert_eq!(
+            parse(":filter 8..16.6"),
+            Command::Filter {
+                min: 8.0,
+                max: Some(16.6)
+            }
+        );
+        assert_eq!(
+            parse(":filter 16.6.."),
+            Command::Filter {
+                min: 16.6,
+                max: None
+            }
+        );
+        assert_eq!(parse(":filter clear"), Command::FilterClear);
+        assert_eq!(error(":filter 9..5"), "invalid duration range '9..5'");
+        assert_eq!(error(":filter ..5"), "invalid duration range '..5'");
+
+        assert_eq!(
+            parse(r#":mark 4 "stutter on load""#),
+            Command::Mark {
+                frame_id: 4,
+                note: "stutter on load".to_string()
+            }
+        );
+        assert!(error(":mark x note").starts_with("usage:"));
+    }
+
+    #[test]
+    fn test_overlays() {
+        assert_eq!(
+            parse(":compliance"),
+            Command::Compliance(Some(crate::DEFAULT_COMPLIANCE_WINDOW))
+        );
+        assert_eq!(parse(":compliance 0"), Command::Compliance(Some(1)));
+        assert_eq!(parse(":compliance off"), Command::Compliance(None));
+        assert!(error(":compliance ten").starts_with("usage:"));
+
+        assert_eq!(parse(":avg 30"), Command::Average(Some(30)));
+        assert_eq!(parse(":avg 0"), Command::Average(None));
+        assert_eq!(parse(":avg"), Command::Average(None));
+        assert!(error(":avg -1").starts_with("usage:"));
+
+        assert_eq!(parse(":fps"), Command::Fps);
+        assert_eq!(parse(":grid"), Command::Grid);
+        assert_eq!(parse(":hist"), Command::Histogram(None));
+        assert_eq!(parse(":hist 12"), Command::Histogram(Some(12)));
+        assert!(error(":hist 0").starts_with("usage:"));
+    }
+
+    #[test]
+    fn test_layout_export_and_screenshot() {
+        assert_eq!(
+            parse(":layout save work"),
+            Command::LayoutSave("work".to_string())
+        );
+        assert_eq!(
+            parse(":layout load work"),
+            Command::LayoutLoad("work".to_string())
+        );
+        assert!(error(":layout drop work").starts_with("usage:"));
+        assert_eq!(
+            parse(":w s.json"),
+            Command::Write(Some("s.json".to_string()))
+        );
+        assert_eq!(parse(":w"), Command::Write(None));
+
+        let (width, height) = export::DEFAULT_THUMBNAIL_SIZE;
+        assert_eq!(
+            parse(":export thumb t.png"),
+            Command::ExportThumb {
+                path: "t.png".to_string(),
+                width,
+                height
+            }
+        );
+        assert_eq!(
+            error(":export thumb t.png big"),
+            "invalid size, expected WxH"
+        );
+        assert_eq!(
+            parse(":export csv o.csv"),
+            Command::ExportCsv("o.csv".to_string())
+        );
+        assert_eq!(
+            parse(":export chrome t.json"),
+            Command::ExportChrome("t.json".to_string())
+        );
+        assert_eq!(
+            parse(":export folded f.txt"),
+            Command::ExportFolded("f.txt".to_string())
+        );
+        assert_eq!(
+            parse(":export stats s.json"),
+            Command::ExportStats("s.json".to_string())
+        );
+        assert!(error(":export csv").starts_with("usage:"));
+
+        assert_eq!(
+            parse(":screenshot s.txt plain"),
+            Command::Screenshot {
+                path: "s.txt".to_string(),
+                ansi: false
+            }
+        );
+        assert!(error(":screenshot").starts_with("usage:"));
+    }
+
+    #[test]
+    fn test_analysis_commands() {
+        assert_eq!(parse(":stats"), Command::Stats);
+        assert_eq!(parse(":gaps"), Command::Gaps);
+        assert_eq!(parse(":gaps off"), Command::GapsOff);
+        assert!(error(":gaps on").starts_with("usage:"));
+        assert_eq!(parse(":regressions"), Command::Regressions);
+        assert_eq!(parse(":r"), Command::Reload);
+        assert_eq!(parse(":agg target"), Command::AggTarget);
+        assert!(error(":agg").starts_with("usage:"));
+        assert_eq!(parse(":top"), Command::Top(None));
+        assert_eq!(parse(":top 3"), Command::Top(Some(3)));
+        assert!(error(":top 0").starts_with("usage:"));
+        assert_eq!(parse(":sort name"), Command::Sort(ChildOrder::Name));
+        assert!(error(":sort size").starts_with("usage:"));
+        assert_eq!(
+            parse(":search draw  walls"),
+            Command::Search("draw walls".to_string())
+        );
+        assert!(error(":search").starts_with("usage:"));
+        assert_eq!(
+            parse(":compare-runs new.json"),
+            Command::CompareRuns {
+                base: None,
+                new: "new.json".to_string()
+            }
+        );
+        assert_eq!(
+            parse(":compare-runs a.json b.json"),
+            Command::CompareRuns {
+                base: Some("a.json".to_string()),
+                new: "b.json".to_string()
+            }
+        );
+        assert!(error(":compare-runs").starts_with("usage:"));
+        assert_eq!(parse(":goto most-children"), Command::GotoMostChildren);
+        assert!(error(":goto").starts_with("usage:"));
+    }
+
+    #[test]
+    fn test_merge_and_highlights() {
+        assert_eq!(parse(":merge 4"), Command::Merge(Some(4)));
+        assert_eq!(parse(":merge off"), Command::Merge(None));
+        assert_eq!(error(":merge 0"), "invalid merge size '0'");
+        assert!(error(":merge").starts_with("usage:"));
+
+        assert_eq!(
+            parse(":find message load failed"),
+            Command::FindMessage("load failed".to_string())
+        );
+        assert_eq!(parse(":find message"), Command::ClearHighlight);
+        assert!(error(":find").starts_with("usage:"));
+        assert_eq!(
+            parse(":first-over 16ms"),
+            Command::FirstOver(Duration::from_millis(16))
+        );
+        assert!(error(":first-over 16parsecs").starts_with("unknown duration unit"));
+        assert!(error(":first-over").starts_with("usage:"));
+        assert_eq!(
+            parse(":spikes"),
+            Command::Spikes(crate::DEFAULT_SPIKE_STDDEVS)
+        );
+        assert_eq!(parse(":spikes 2.5"), Command::Spikes(2.5));
+        assert!(error(":spikes -1").starts_with("usage:"));
+        assert_eq!(parse(":idle-warn 0.5"), Command::IdleWarn(0.5));
+        assert_eq!(parse(":idle-warn off"), Command::ClearHighlight);
+        assert_eq!(error(":idle-warn half"), "invalid idle fraction 'half'");
+    }
+
+    #[test]
+    fn test_display_commands() {
+        assert_eq!(parse(":radix hex"), Command::Radix(Radix::Hex));
+        assert!(error(":radix oct").starts_with("usage:"));
+        assert_eq!(parse(":precision 3"), Command::Precision(3));
+        assert!(error(":precision 10").starts_with("usage:"));
+        assert_eq!(parse(":timeline"), Command::Timeline);
+        assert_eq!(parse(":yank"), Command::Yank);
+        assert_eq!(parse(":zoom-to-selection"), Command::ZoomToSelection);
+        assert_eq!(
+            parse(":level warn"),
+            Command::Level(Some("WARN".to_string()))
+        );
+        assert_eq!(parse(":level all"), Command::Level(None));
+        assert!(error(":level loud").starts_with("usage:"));
+        assert_eq!(parse(":view interval"), Command::View(View::Interval));
+        assert_eq!(parse(":cdf"), Command::View(View::Cdf));
+        assert_eq!(parse(":stacked"), Command::View(View::Stacked));
+        assert_eq!(parse(":view donut"), Command::DetailMode(DetailMode::Donut));
+        assert!(error(":view pie").starts_with("usage:"));
+        assert_eq!(parse(":y log2"), Command::Scale(Scale::Log2));
+        assert!(error(":y log3").starts_with("usage:"));
+        assert_eq!(parse(":scale log"), Command::Scale(Scale::Log10));
+        assert!(error(":scale dual").starts_with("usage:"));
+    }
+
+    #[test]
+    fn test_unknown_command() {
+        assert_eq!(error(":frobnicate"), "unknown command ':frobnicate'");
+        assert_eq!(error(""), "empty command");
+    }
+
+    #[test]
+    fn test_completions() {
+        assert_eq!(completions(":fp"), vec![":fps"]);
+        assert_eq!(completions(":fi"), vec![":filter", ":find", ":first-over"]);
+        assert_eq!(
+            completions(":sort "),
+            vec![":sort dur", ":sort name", ":sort orig"]
+        );
+        assert_eq!(completions(":f in"), vec![":f inspect"]);
+        assert_eq!(
+            completions(":f inspect m"),
+            vec![":f inspect max", ":f inspect min"]
+        );
+        assert_eq!(completions(":exclude o"), vec![":exclude off"]);
+        assert!(completions(":hist ").is_empty());
+        assert!(completions(":nope").is_empty());
+    }
+}
@@ -0,0 +1,199 @@
+use std::collections::HashMap;
+
+use crate::format::fmt_millis;
+use crate::stats;
+use crate::trace::FrameTrace;
+
+/// number of targets listed by growth of their time per frame
+const TOP_TARGETS: usize = 5;
+
+/// Key frame time statistics of one run.
+struct RunSummary {
+    mean: f64,
+    median: f64,
+    p95: f64,
+    p99: f64,
+    max: f64,
+    /// fraction of frames within the budget
+    compliance: f64,
+    /// time per frame of each child target, in ms
+    target_millis: HashMap<String, f64>,
+}
+
+impl RunSummary {
+    fn new(frames: &[FrameTrace], budget: f64) -> RunSummary {
+        let mut sorted: Vec<f64> = frames
+            .iter()
+            .map(|f| f.trace.total_duration().as_millis_f64())
+            .collect();
+        sorted.sort_by(|a, b| a.partial_cmp(b).unwrap());
+        let within = sorted.iter().filter(|ms| **ms <= budget).count();
+
+        let mut target_millis = HashMap::new();
+        for frame in frames {
+            for child in &frame.child_traces {
+                *target_millis.entry(child.target.clone()).or_insert(0.0) +=
+                    child.total_duration().as_millis_f64() / frames.len() as f64;
+            }
+        }
+
+        RunSummary {
+            mean: stats::mean_stddev(&sorted).map_or(0.0, |(mean, _)| mean),
+            median: stats::median(&sorted),
+            p95: stats::percentile(&sorted, 0.95),
+            p99: stats::percentile(&sorted, 0.99),
+            max: sorted.last().copied().unwrap_or(0.0),
+            compliance: stats::ratio(within as f64, sorted.len() as f64).unwrap_or(1.0),
+            target_millis,
+        }
+    }
+}
+
+fn fmt_delta(delta: f64, precision: usize) -> String {
+    let sign = if delta < 0.0 { '-' } else { '+' };
+    format!("{}{}", sign, fmt_millis(delta.abs(), precision))
+}
+
+fn fmt_change(base: f64, new: f64) -> String {
+    match stats::ratio(new - base, base) {
+        Some(change) => format!("{:+.1}%", change * 100.0),
+        None => "-".to_string(),
+    }
+}
+
+/// Side by side table of the frame time statistics of two runs, with the
+/// targets whose time per frame grew the most.
+pub fn compare_runs(
+    base: &[FrameTrace],
+    new: &[FrameTrace],
+    budget: f64,
+    precision: usize,
+) -> String {
+    let a = RunSummary::new(base, budget);
+    let b = RunSummary::new(new, budget);
+
+    let mut text = format!(
+        "{:<12}{:>12}{:>12}{:>12}{:>10}\n",
+        "", "base", "new", "delta", "change"
+    );
+    text.push_str(&format!(
+        "{:<12}{:>12}{:>12}{:>12}{:>10}\n",
+        "frames",
+        base.len(),
+        new.len(),
+        format!("{:+}", new.len() as i64 - base.len() as i64),
+        fmt_change(base.len() as f64, new.len() as f64)
+    ));
+    for (name, x, y) in [
+        ("mean", a.mean, b.mean),
+        ("median", a.median, b.median),
+        ("p95", a.p95, b.p95),
+        ("p99", a.p99, b.p99),
+        ("max", a.max, b.max),
+    ] {
+        text.push_str(&format!(
+            "{:<12}{:>12}{:>12}{:>12}{:>10}\n",
+            name,
+            fmt_millis(x, precision),
+            fmt_millis(y, precision),
+            fmt_delta(y - x, precision),
+            fmt_change(x, y)
+        ));
+    }
+    text.push_str(&format!(
+        "{:<12}{:>11.1}%{:>11.1}%{:>12}\n",
+        "compliance",
+        a.compliance * 100.0,
+        b.compliance * 100.0,
+        format!("{:+.1}pp", (b.compliance - a.compliance) * 100.0)
+    ));
+
+    let mut growth: Vec<(&str, f64, f64)> = b
+        .target_millis
+        .iter()
+        .map(|(target, y)| {
+            let x = a.target_millis.get(target).copied().unwrap_or(0.0);
+            (target.as_str(), x, *y)
+        })
+        .filter(|(_, x, y)| y > x)
+        .collect();
+    growth.sort_by(|l, r| (r.2 - r.1).partial_cmp(&(l.2 - l.1)).unwrap());
+
+    text.push_str("\nTargets grown most (time per frame):\n");
+    if growth.is_empty() {
+        text.push_str("  none\n");
+    }
+    for (target, x, y) in growth.into_iter().take(TOP_TARGETS) {
+        text.push_str(&format!(
+            "  {} {} -> {} ({}, {})\n",
+            target,
+            fmt_millis(x, precision),
+            fmt_millis(y, precision),
+            fmt_delta(y - x, precision),
+            fmt_change(x, y)
+        ));
+    }
+    text
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use super::*;
+    use crate::trace::{self, FrameWindow};
+
+    fn run(name: &str) -> Vec<FrameTrace> {
+        let path = Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("testdata")
+            .join(name);
+        trace::read_trace_file(&path, trace::DEFAULT_FRAME_SPAN, FrameWindow::default(), 1)
+            .unwrap()
+            .0
+    }
+
+    fn row(text: &str, name: &str) -> Vec<String> {
+        let line = text.lines().find(|l| l.starts_with(name)).unwrap();
+        line.split_whitespace().map(str::to_string).collect()
+    }
+
+    #[test]
+    fn test_compare_runs() {
+        let text = compare_runs(&run("compare_base.json"), &run("compare_new.json"), 4.5, 2);
+        assert_eq!(
+            row(&text, "mean"),
+            ["mean", "4.00ms", "5.00ms", "+1.00ms", "+25.0%"]
+        );
+        assert_eq!(
+            row(&text, "max"),
+            ["max", "4.00ms", "6.00ms", "+2.00ms", "+50.0%"]
+        );
+        assert_eq!(
+            row(&text, "compliance"),
+            ["compliance", "100.0%", "50.0%", "-50.0pp"]
+        );
+        let targets: Vec<&str> = text
+            .lines()
+            .skip_while(|l| !l.starts_with("Targets grown most"))
+            .skip(1)
+            .collect();
+        assert_eq!(
+            targets,
+            [
+                "  iw::draw 2.00ms -> 3.00ms (+1.00ms, +50.0%)",
+                "  iw::input 1.00ms -> 1.25ms (+250.00µs, +25.0%)",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_compare_runs_empty_base() {
+        let text = compare_runs(&[], &run("compare_new.json"), 4.5, 2);
+        assert_eq!(row(&text, "frames"), ["frames", "0", "2", "+2", "-"]);
+        assert_eq!(
+            row(&text, "mean"),
+            ["mean", "0ns", "5.00ms", "+5.00ms", "-"]
+        );
+        assert!(text.contains("  iw::draw 0ns -> 3.00ms (+3.00ms, -)\n"));
+    }
+}
@@ -0,0 +1,372 @@
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+use serde::Serialize;
+
+use crate::stats;
+use crate::trace::{FrameTrace, SpanNode, Trace};
+
+const BACKGROUND: [u8; 3] = [0x1e, 0x1e, 0x1e];
+const BAR: [u8; 3] = [0xc0, 0x3c, 0xc0];
+
+/// Writes a `width`x`height` PNG bar chart of `values`, where `max` maps to
+/// the full image height. Values are downsampled to one bar per pixel column.
+pub fn write_thumbnail(
+    path: &Path,
+    values: &[f64],
+    max: f64,
+    width: u32,
+    height: u32,
+) -> Result<(), String> {
+    let columns = stats::bucket_max(values, width as usize);
+    let mut pixels = Vec::with_capacity((width * height * 3) as usize);
+    for row in 0..height {
+        // fraction of the chart height at the center of this pixel row
+        let level = (height - row) as f64 - 0.5;
+        for col in 0..width as usize {
+            let bar = match columns.get(col) {
+                Some(value) if max > 0.0 => value / max * height as f64,
+                _ => 0.0,
+            };
+            pixels.extend_from_slice(if bar >= level { &BAR } else { &BACKGROUND });
+        }
+    }
+
+    let file = File::create(path).map_err(|e| e.to_string())?;
+    let mut encoder = png::Encoder::new(BufWriter::new(file), width, height);
+    encoder.set_color(png::ColorType::Rgb);
+    encoder.set_depth(png::BitDepth::Eight);
+    let mut writer = encoder.write_header().map_err(|e| e.to_string())?;
+    writer.write_image_data(&pixels).map_err(|e| e.to_string())
+}
+
+/// Parses a `WxH` size like `160x40`.
+pub fn parse_size(s: &str) -> Option<(u32, u32)> {
+    let (w, h) = s.split_once('x')?;
+    let (w, h) = (w.parse().ok()?, h.parse().ok()?);
+    if w == 0 || h == 0 || w > MAX_THUMBNAIL_SIZE || h > MAX_THUMBNAIL_SIZE {
+        return None;
+    }
+    Some((w, h))
+}
+
+pub const DEFAULT_THUMBNAIL_SIZE: (u32, u32) = (160, 40);
+const MAX_THUMBNAIL_SIZE: u32 = 4096;
+
+/// Quotes a CSV field if it contains a separator, quote or line break.
+fn csv_field(s: &str) -> String {
+    if s.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", s.replace('"', "\"\""))
+    } else {
+        s.to_string()
+    }
+}
+
+/// Writes one CSV row per `(frame id, frame)`, durations in ms.
+pub fn write_csv<'a>(
+    path: &Path,
+    frames: impl Iterator<Item = (u64, &'a FrameTrace)>,
+) -> Result<usize, String> {
+    let file = File::create(path).map_err(|e| e.to_string())?;
+    let mut w = BufWriter::new(file);
+    let mut rows = 0;
+    writeln!(w, "frame_id,target,total_ms,busy_ms,idle_ms,child_count")
+        .map_err(|e| e.to_string())?;
+    for (id, frame_trace) in frames {
+        let trace = &frame_trace.trace;
+        writeln!(
+            w,
+            "{},{},{},{},{},{}",
+            id,
+            csv_field(&trace.target),
+            trace.total_duration().as_millis_f64(),
+            trace.busy().as_millis_f64(),
+            trace.idle().as_millis_f64(),
+            frame_trace.child_traces.len()
+        )
+        .map_err(|e| e.to_string())?;
+        rows += 1;
+    }
+    w.flush().map_err(|e| e.to_string())?;
+    Ok(rows)
+}
+
+/// An event of the Chrome trace event format, times in µs.
+#[derive(Serialize)]
+struct ChromeEvent<'a> {
+    name: &'a str,
+    #[serde(skip_serializing_if = "str::is_empty")]
+    cat: &'a str,
+    ph: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    ts: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    dur: Option<f64>,
+    pid: u32,
+    tid: usize,
+    args: serde_json::Value,
+}
+
+impl<'a> ChromeEvent<'a> {
+    /// A complete event of `trace` starting at `start`.
+    fn complete(trace: &'a Trace, start: f64, tid: usize, args: serde_json::Value) -> Self {
+        ChromeEvent {
+            name: &trace.span.name,
+            cat: &trace.target,
+            ph: "X",
+            ts: Some(start),
+            dur: Some(micros(trace)),
+            pid: 1,
+            tid,
+            args,
+        }
+    }
+
+    /// Names the track `tid`.
+    fn thread_name(tid: usize, name: &'a str) -> Self {
+        ChromeEvent {
+            name: "thread_name",
+            cat: "",
+            ph: "M",
+            ts: None,
+            dur: None,
+            pid: 1,
+            tid,
+            args: serde_json::json!({ "name": name }),
+        }
+    }
+}
+
+fn micros(trace: &Trace) -> f64 {
+    trace.total_duration().as_secs_f64() * 1e6
+}
+
+/// When `trace` started, in µs since the epoch, going by the timestamp of
+/// its close.
+fn start_micros(trace: &Trace) -> Option<f64> {
+    trace
+        .timestamp
+        .map(|timestamp| timestamp.timestamp_micros() as f64 - micros(trace))
+}
+
+/// Writes `(frame id, frame)`s in the Chrome trace event format, for
+/// chrome://tracing and Perfetto. Frames are on one track and their children
+/// on a track per target. Times are µs since the start of the first frame,
+/// frames without a timestamp start where the one before ended and their
+/// children one after the other.
+pub fn write_chrome_trace<'a>(
+    path: &Path,
+    frames: impl Iterator<Item = (u64, &'a FrameTrace)>,
+) -> Result<usize, String> {
+    let mut events = vec![ChromeEvent::thread_name(0, "frames")];
+    let mut targets: Vec<&str> = Vec::new();
+    let mut origin = None;
+    // end of the last frame, since `origin`
+    let mut end = 0.0;
+    let mut count = 0;
+    for (id, frame_trace) in frames {
+        let trace = &frame_trace.trace;
+        let start = match start_micros(trace) {
+            // the first timestamp continues after the frames without one
+            Some(start) => start - *origin.get_or_insert(start - end),
+            None => end,
+        };
+        events.push(ChromeEvent::complete(
+            trace,
+            start,
+            0,
+            serde_json::json!({ "id": id }),
+        ));
+        let mut child_start = start;
+        for child in &frame_trace.child_traces {
+            let tid = match targets.iter().position(|target| *target == child.target) {
+                Some(i) => i + 1,
+                None => {
+                    targets.push(&child.target);
+                    events.push(ChromeEvent::thread_name(targets.len(), &child.target));
+                    targets.len()
+                }
+            };
+            let start = match (start_micros(child), origin, trace.timestamp) {
+                // within the frame, timestamps may be too coarse for that
+                (Some(child_start), Some(origin), Some(_)) => (child_start - origin).max(start),
+                _ => child_start,
+            };
+            events.push(ChromeEvent::complete(
+                child,
+                start,
+                tid,
+                serde_json::json!({ "level": child.level }),
+            ));
+            child_start = start + micros(child);
+        }
+        end = start + micros(trace);
+        count += 1;
+    }
+
+    let file = File::create(path).map_err(|e| e.to_string())?;
+    let mut w = BufWriter::new(file);
+    serde_json::to_writer(
+        &mut w,
+        &serde_json::json!({ "traceEvents": events, "displayTimeUnit": "ms" }),
+    )
+    .map_err(|e| e.to_string())?;
+    w.flush().map_err(|e| e.to_string())?;
+    Ok(count)
+}
+
+/// A frame of a folded stack, without the `;` separating them.
+fn folded_name(trace: &Trace) -> String {
+    format!("{}/{}", trace.target, trace.span.name).replace(';', ":")
+}
+
+/// Adds the self time in µs of `node` and its descendants under `stack` to
+/// `stacks`.
+fn fold(node: &SpanNode, stack: &str, stacks: &mut BTreeMap<String, u64>) {
+    let stack = format!("{};{}", stack, folded_name(node.trace));
+    let children: std::time::Duration =
+        node.children.iter().map(|c| c.trace.total_duration()).sum();
+    let own = node.trace.total_duration().saturating_sub(children);
+    *stacks.entry(stack.clone()).or_default() += own.as_micros() as u64;
+    for child in &node.children {
+        fold(child, &stack, stacks);
+    }
+}
+
+/// Writes the frames in the folded stack format of `flamegraph.pl` and
+/// inferno: a line per stack of the span tree with its self time in µs,
+/// summed over all frames. Stacks start at the frame span, returns the
+/// number of lines.
+pub fn write_folded<'a>(
+    path: &Path,
+    frames: impl Iterator<Item = &'a FrameTrace>,
+) -> Result<usize, String> {
+    let mut stacks = BTreeMap::new();
+    for frame_trace in frames {
+        let root = frame_trace.trace.span.name.replace(';', ":");
+        let order: Vec<usize> = (0..frame_trace.child_traces.len()).collect();
+        let tree = frame_trace.span_tree(&order);
+        let children: std::time::Duration =
+            tree.iter().map(|node| node.trace.total_duration()).sum();
+        let own = frame_trace.trace.total_duration().saturating_sub(children);
+        *stacks.entry(root.clone()).or_default() += own.as_micros() as u64;
+        for node in &tree {
+            fold(node, &root, &mut stacks);
+        }
+    }
+
+    let file = File::create(path).map_err(|e| e.to_string())?;
+    let mut w = BufWriter::new(file);
+    let mut lines = 0;
+    // stacks without time of their own are still drawn through their children
+    for (stack, micros) in stacks.iter().filter(|(_, micros)| **micros > 0) {
+        writeln!(w, "{} {}", stack, micros).map_err(|e| e.to_string())?;
+        lines += 1;
+    }
+    w.flush().map_err(|e| e.to_string())?;
+    Ok(lines)
+}
+
+/// Writes `value` as pretty printed JSON.
+pub fn write_json(path: &Path, value: &impl Serialize) -> Result<(), String> {
+    let file = File::create(path).map_err(|e| e.to_string())?;
+    let mut w = BufWriter::new(file);
+    serde_json::to_writer_pretty(&mut w, value).map_err(|e| e.to_string())?;
+    writeln!(w).map_err(|e| e.to_string())?;
+    w.flush().map_err(|e| e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::trace::{FrameWindow, read_trace_file};
+
+    fn read_frames(name: &str, frame_span: &str) -> Vec<FrameTrace> {
+        let path = Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("testdata")
+            .join(name);
+        read_trace_file(&path, frame_span, FrameWindow::default(), 1)
+            .unwrap()
+            .0
+    }
+
+    /// (name, tid, ts, dur) of the complete events written for `frames`.
+    fn chrome_events(frames: &[FrameTrace]) -> Vec<(String, u64, i64, i64)> {
+        let path = std::env::temp_dir().join(format!("terminus-{}.trace.json", std::process::id()));
+        let ids = frames.iter().enumerate().map(|(i, f)| (f.id(i), f));
+        assert_eq!(write_chrome_trace(&path, ids).unwrap(), frames.len());
+        let json: serde_json::Value = serde_json::from_reader(File::open(&path).unwrap()).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        json["traceEvents"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .filter(|event| event["ph"] == "X")
+            .map(|event| {
+                (
+                    event["name"].as_str().unwrap().to_string(),
+                    event["tid"].as_u64().unwrap(),
+                    event["ts"].as_f64().unwrap().round() as i64,
+                    event["dur"].as_f64().unwrap().round() as i64,
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_chrome_trace() {
+        let mut frames = read_frames("tick_spans.json", "tick");
+        let event = |name: &str, tid, ts, dur| (name.to_string(), tid, ts, dur);
+        // µs since the start of the first frame, a track per target
+        assert_eq!(
+            chrome_events(&frames),
+            [
+                event("tick", 0, 0, 2000),
+                event("render", 1, 0, 1000),
+                event("tick", 0, 1000, 4000),
+                event("physics", 2, 2500, 500),
+                event("render", 1, 1000, 3000),
+            ]
+        );
+
+        // without timestamps frames and their children are laid end to end
+        for frame_trace in &mut frames {
+            frame_trace.trace.timestamp = None;
+            for child in &mut frame_trace.child_traces {
+                child.timestamp = None;
+            }
+        }
+        assert_eq!(
+            chrome_events(&frames),
+            [
+                event("tick", 0, 0, 2000),
+                event("render", 1, 0, 1000),
+                event("tick", 0, 2000, 4000),
+                event("physics", 2, 2000, 500),
+                event("render", 1, 2500, 3000),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_folded_stacks() {
+        let frames = read_frames("nested_spans.json", crate::trace::DEFAULT_FRAME_SPAN);
+        let path = std::env::temp_dir().join(format!("terminus-{}.folded", std::process::id()));
+        // the same frame twice sums up
+        let lines = write_folded(&path, frames.iter().chain(&frames)).unwrap();
+        let folded = std::fs::read_to_string(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(lines, 5);
+        // self time in µs, without the time of the children
+        assert_eq!(
+            folded,
+            "frame 2000\n\
+             frame;iw::draw/draw 1000\n\
+             frame;iw::draw/draw;iw::draw/sprites 1000\n\
+             frame;iw::draw/draw;iw::draw/walls 2000\n\
+             frame;iw::input/input 2000\n"
+        );
+    }
+}
@@ -0,0 +1,193 @@
+use std::fs::{self, File};
+use std::io::{BufRead, BufReader, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+
+use crate::trace::{
+    FrameTrace, Lifecycles, SkippedLine, Trace, TraceError, describe_error, group_frames,
+};
+
+/// What one read of a followed file got: the frames completed, the lines
+/// that aren't valid traces and were skipped, and the error that stopped the
+/// read early if any.
+#[derive(Default)]
+pub struct Polled {
+    pub frames: Vec<FrameTrace>,
+    pub skipped: Vec<SkippedLine>,
+    pub error: Option<TraceError>,
+}
+
+/// Tails a trace file that is still being written. Frames are returned as
+/// soon as their frame span line is complete. If the file is rotated
+/// (replaced by a new file at the same path) or truncated, reading continues
+/// from the start of the new content.
+pub struct Follower {
+    path: PathBuf,
+    reader: BufReader<File>,
+    /// byte offset up to which complete lines have been read
+    offset: u64,
+    /// number of complete lines read
+    lines: usize,
+    /// an incomplete last line, waiting for the rest
+    partial: String,
+    /// children read so far that don't have their frame yet
+    pending: Vec<Trace>,
+    /// spans entered but not exited yet
+    lifecycles: Lifecycles,
+    /// name of the span that ends a frame
+    frame_span: String,
+    #[cfg(unix)]
+    inode: u64,
+}
+
+impl Follower {
+    /// Opens `path` and reads all frames in it so far, frames end at spans
+    /// named `frame_span`.
+    pub fn open(path: &Path, frame_span: &str) -> Result<(Follower, Polled), TraceError> {
+        let file = File::open(path).map_err(|source| io_error(path, source))?;
+        #[cfg(unix)]
+        let inode = {
+            use std::os::unix::fs::MetadataExt;
+            file.metadata()
+                .map_err(|source| io_error(path, source))?
+                .ino()
+        };
+        let mut follower = Follower {
+            path: path.to_path_buf(),
+            reader: BufReader::new(file),
+            offset: 0,
+            lines: 0,
+            partial: String::new(),
+            pending: Vec::new(),
+            lifecycles: Lifecycles::default(),
+            frame_span: frame_span.to_string(),
+            #[cfg(unix)]
+            inode,
+        };
+        let polled = follower.read_available();
+        Ok((follower, polled))
+    }
+
+    /// Returns the frames appended since the last call.
+    pub fn poll(&mut self) -> Polled {
+        // drain what is left in the current file before looking for a rotation
+        let mut polled = self.read_available();
+        if polled.error.is_none() {
+            match self.reopen_if_replaced() {
+                Ok(true) => {
+                    let reopened = self.read_available();
+                    polled.frames.extend(reopened.frames);
+                    polled.skipped.extend(reopened.skipped);
+                    polled.error = reopened.error;
+                }
+                Ok(false) => {}
+                Err(e) => polled.error = Some(e),
+            }
+        }
+        polled
+    }
+
+    /// Reads the complete lines appended so far. Lines that aren't valid
+    /// traces are skipped like [`crate::trace::read_trace_file`] does, they
+    /// would otherwise be read again on every poll.
+    fn read_available(&mut self) -> Polled {
+        let mut traces = Vec::new();
+        let mut polled = Polled::default();
+        loop {
+            let read = match self.reader.read_line(&mut self.partial) {
+                Ok(read) => read,
+                Err(source) => {
+                    polled.error = Some(io_error(&self.path, source));
+                    break;
+                }
+            };
+            if read == 0 || !self.partial.ends_with('\n') {
+                break;
+            }
+            self.offset += self.partial.len() as u64;
+            self.lines += 1;
+            let line = std::mem::take(&mut self.partial);
+            let line = line.trim();
+            if !line.is_empty() {
+                match serde_json::from_str::<Trace>(line) {
+                    Ok(trace) => traces.extend(self.lifecycles.push(trace)),
+                    Err(e) => polled.skipped.push((self.lines, describe_error(e))),
+                }
+            }
+        }
+        polled.frames = group_frames(traces, &mut self.pending, &self.frame_span);
+        polled
+    }
+
+    /// Reopens or rewinds the file if it was replaced or truncated, returns
+    /// whether it did.
+    fn reopen_if_replaced(&mut self) -> Result<bool, TraceError> {
+        let Ok(metadata) = fs::metadata(&self.path) else {
+            // rotated away and not recreated yet
+            return Ok(false);
+        };
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::MetadataExt;
+            if metadata.ino() != self.inode {
+                let file = File::open(&self.path).map_err(|source| io_error(&self.path, source))?;
+                self.inode = metadata.ino();
+                self.reader = BufReader::new(file);
+                self.restart();
+                return Ok(true);
+            }
+        }
+
+        if metadata.len() < self.offset {
+            self.reader
+                .seek(SeekFrom::Start(0))
+                .map_err(|source| io_error(&self.path, source))?;
+            self.restart();
+            return Ok(true);
+        }
+        Ok(false)
+    }
+
+    fn restart(&mut self) {
+        self.offset = 0;
+        self.lines = 0;
+        self.partial.clear();
+        self.pending.clear();
+        self.lifecycles = Lifecycles::default();
+    }
+}
+
+fn io_error(path: &Path, source: std::io::Error) -> TraceError {
+    TraceError::Io {
+        name: path.display().to_string(),
+        source,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+
+    const TICK: &str = r#"{"timestamp":"2024-12-28T17:50:48.002000Z","level":"INFO","fields":{"message":"close","time.busy":"2.00ms","time.idle":"0ns"},"target":"engine","span":{"id":1,"name":"tick"},"spans":[]}"#;
+
+    #[test]
+    fn test_malformed_lines_are_skipped_once() {
+        let path = std::env::temp_dir().join(format!("follow-{}.json", std::process::id()));
+        let mut file = File::create(&path).unwrap();
+        // valid JSON but not a trace, then a trace
+        writeln!(file, "{TICK}\n{{\"level\":\"INFO\"}}\n{TICK}").unwrap();
+
+        let (mut follower, polled) = Follower::open(&path, "tick").unwrap();
+        assert!(polled.error.is_none());
+        assert_eq!(polled.frames.len(), 2);
+        assert_eq!(polled.skipped.len(), 1);
+        assert_eq!(polled.skipped[0].0, 2);
+
+        writeln!(file, "{TICK}").unwrap();
+        let polled = follower.poll();
+        assert_eq!(polled.frames.len(), 1);
+        assert!(polled.skipped.is_empty());
+        fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,61 @@
+use std::time::Duration;
+
+pub const DEFAULT_PRECISION: usize = 2;
+
+/// Formats a duration in the largest unit (ns, µs, ms, s) that keeps the
+/// integer part non-zero, with `precision` digits after the decimal point.
+/// Nanoseconds are always whole numbers.
+pub fn fmt_duration(d: Duration, precision: usize) -> String {
+    let nanos = d.as_nanos();
+    if nanos < 1_000 {
+        return format!("{}ns", nanos);
+    }
+    let mut value = nanos as f64 / 1e3;
+    for unit in ["µs", "ms"] {
+        let text = format!("{:.*}", precision, value);
+        // rounding can carry into the next unit, 999.999µs is 1.00ms
+        if text.parse::<f64>().is_ok_and(|rounded| rounded < 1000.0) {
+            return text + unit;
+        }
+        value /= 1e3;
+    }
+    format!("{:.*}s", precision, value)
+}
+
+/// [`fmt_duration`] for a duration given in (fractional) milliseconds.
+pub fn fmt_millis(millis: f64, precision: usize) -> String {
+    fmt_duration(Duration::from_secs_f64(millis.max(0.0) / 1e3), precision)
+}
+
+/// Base frame ids are displayed in.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Radix {
+    Dec,
+    Hex,
+}
+
+pub fn fmt_id(id: u64, radix: Radix) -> String {
+    match radix {
+        Radix::Dec => id.to_string(),
+        Radix::Hex => format!("{:#x}", id),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fmt_duration_units() {
+        let fmt = |nanos| fmt_duration(Duration::from_nanos(nanos), DEFAULT_PRECISION);
+        assert_eq!(fmt(0), "0ns");
+        assert_eq!(fmt(999), "999ns");
+        assert_eq!(fmt(1_000), "1.00µs");
+        assert_eq!(fmt(999_994), "999.99µs");
+        assert_eq!(fmt(999_999), "1.00ms");
+        assert_eq!(fmt(2_930_000), "2.93ms");
+        assert_eq!(fmt(999_999_999), "1.00s");
+        assert_eq!(fmt(1_234_567_000), "1.23s");
+        assert_eq!(fmt_duration(Duration::from_nanos(1_500), 0), "2µs");
+    }
+}
@@ -0,0 +1,150 @@
+use ratatui::prelude::*;
+use ratatui::widgets::{Block, Clear, Paragraph};
+
+/// Commands of the command line with their description, shown by `?`.
+pub const COMMANDS: &[(&str, &str)] = &[
+    (":q", "quit"),
+    (":r", "reload the trace files"),
+    (":f all", "chart all frames"),
+    (":f <a>..<b>", "chart frames a to b"),
+    (
+        ":f inspect <max|min|id>",
+        "show a frame in the detail panel",
+    ),
+    (":f goto <id>", "inspect a frame and center the chart on it"),
+    (":f diff <id1> <id2>", "compare the children of two frames"),
+    (":exclude <a>..<b>|off", "leave frames a to b out"),
+    (
+        ":filter <min>..[max]|clear",
+        "only frames taking min to max ms",
+    ),
+    (
+        ":level <info|warn|error|all>",
+        "only frames and children at a level",
+    ),
+    (":merge <k>|off", "chart every k frames as one"),
+    (
+        ":view <frames|interval|areas|histogram|cdf|stacked>",
+        "what the chart shows",
+    ),
+    (
+        ":view <list|table|donut|timeline>",
+        "what the detail panel shows",
+    ),
+    (
+        ":timeline",
+        "chart frames over wall-clock time, again for frame order",
+    ),
+    (":hist [bins]", "histogram of the frame durations"),
+    (":cdf", "cumulative distribution of the frame durations"),
+    (":stacked", "busy and idle time of each frame"),
+    (":y <log10|log2|linear|dual>", "y axis scale"),
+    (":scale <log|linear>", "y axis scale"),
+    (":zoom-to-selection", "fit the y axis to the charted frames"),
+    (":compliance [window]|off", "rolling budget compliance"),
+    (":avg <window>", "moving average, 0 removes it"),
+    (":fps", "frames per second overlay"),
+    (":grid", "grid lines on the chart"),
+    (":idle-warn [fraction]|off", "frames mostly idle"),
+    (":spikes [k]", "frames over mean + k stddev, default 3"),
+    (":find message <text>", "frames with a child message"),
+    (":search <text>", "frames with a target, n/N to step"),
+    (
+        ":goto most-children",
+        "inspect the frame with most children",
+    ),
+    (
+        ":first-over <duration>",
+        "inspect the first frame over a duration",
+    ),
+    (
+        ":sort <dur|name|orig>",
+        "order of the inspected frame's children",
+    ),
+    (":mark <id> \"note\"", "note on a frame"),
+    (":stats", "summary of the charted frames"),
+    (":gaps [off]", "missing frame ids, marked on the chart"),
+    (
+        ":yank",
+        "copy the frame detail, or the stats, to the clipboard",
+    ),
+    (":agg target", "child time per target"),
+    (":top [n]", "slowest frames, default 10"),
+    (":regressions", "step changes in frame time"),
+    (
+        ":compare-runs [<base>] <new>",
+        "compare frame stats of two files",
+    ),
+    (":layout <save|load> <name>", "named chart layouts"),
+    (":w [path]", "save the session, to --session without a path"),
+    (":export thumb <path> [WxH]", "PNG thumbnail of the chart"),
+    (":export csv <path>", "charted frames as CSV"),
+    (
+        ":export chrome <path>",
+        "frames and children for chrome://tracing",
+    ),
+    (":export folded <path>", "folded stacks for a flamegraph"),
+    (":export stats <path>", "frame stats of the window as JSON"),
+    (":screenshot <path> [plain]", "screen as text"),
+    (":radix <dec|hex>", "base of frame ids"),
+    (":precision <0-9>", "digits of durations"),
+];
+
+/// Key bindings outside of the command line, shown by `?`.
+pub const KEYS: &[(&str, &str)] = &[
+    (":", "enter a command, Up/Down for history, Tab completes"),
+    (".", "repeat the last command"),
+    ("?", "this help"),
+    ("+ -", "zoom in and out"),
+    ("h l", "pan left and right"),
+    ("j k", "select the next and previous frame"),
+    ("gg G", "jump to the first and last frame"),
+    ("y", "copy the frame detail, or the stats, to the clipboard"),
+    ("n N", "next and previous search match"),
+    ("m", "edit the note of the inspected frame"),
+    ("PgUp PgDn", "scroll the detail panel, also Ctrl+u/Ctrl+d"),
+    ("L", "toggle log and linear scale"),
+    (
+        "Up Down Enter",
+        "pick a target of :agg target and chart its time",
+    ),
+    ("Up Down Enter", "pick a frame of :top and inspect it"),
+    ("mouse", "hover a bar for its frame, click to inspect it"),
+    ("Esc", "clear the search, or chart the frames again"),
+];
+
+/// Bordered list of [`COMMANDS`] and [`KEYS`], centered in the area it is
+/// rendered to.
+pub struct HelpOverlay;
+
+impl Widget for HelpOverlay {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        let width = COMMANDS
+            .iter()
+            .chain(KEYS)
+            .map(|(name, _)| name.chars().count())
+            .max()
+            .unwrap_or(0);
+        let line = |(name, description): &(&str, &'static str)| -> Line<'static> {
+            Line::from(vec![
+                Span::styled(format!("{:width$}  ", name), Style::default().bold()),
+                Span::raw(*description),
+            ])
+        };
+        let mut lines: Vec<Line> = COMMANDS.iter().map(line).collect();
+        lines.push(Line::raw(""));
+        lines.extend(KEYS.iter().map(line));
+
+        let text_width = lines.iter().map(Line::width).max().unwrap_or(0) as u16;
+        let overlay = area.clamp(Rect {
+            x: area.x + area.width.saturating_sub(text_width + 2) / 2,
+            y: area.y + area.height.saturating_sub(lines.len() as u16 + 2) / 2,
+            width: text_width + 2,
+            height: lines.len() as u16 + 2,
+        });
+        Clear.render(overlay, buf);
+        Paragraph::new(lines)
+            .block(Block::bordered().title("Help - any key to close"))
+            .render(overlay, buf);
+    }
+}
@@ -0,0 +1,64 @@
+use std::fs::{self, File};
+use std::path::PathBuf;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{Scale, View};
+
+/// The view-relevant part of the state, with the frame filters, that can be
+/// saved under a name and restored later.
+#[derive(Serialize, Deserialize)]
+#[serde(default)]
+pub struct Layout {
+    pub frame_range: Option<(usize, usize)>,
+    pub scale: Scale,
+    pub view: View,
+    pub compliance_window: Option<usize>,
+    pub excluded: Vec<(u64, u64)>,
+    pub level: Option<String>,
+    pub duration_filter: Option<(f64, Option<f64>)>,
+}
+
+impl Default for Layout {
+    fn default() -> Self {
+        Layout {
+            frame_range: None,
+            scale: Scale::Log10,
+            view: View::Frames,
+            compliance_window: None,
+            excluded: Vec::new(),
+            level: None,
+            duration_filter: None,
+        }
+    }
+}
+
+pub fn config_dir() -> Result<PathBuf, String> {
+    let base = match std::env::var_os("XDG_CONFIG_HOME") {
+        Some(dir) => PathBuf::from(dir),
+        None => PathBuf::from(std::env::var_os("HOME").ok_or("HOME is not set")?).join(".config"),
+    };
+    Ok(base.join("terminus"))
+}
+
+fn layout_path(name: &str) -> Result<PathBuf, String> {
+    if name.is_empty() || name.contains(['/', '\\']) || name.starts_with('.') {
+        return Err(format!("invalid layout name '{}'", name));
+    }
+    Ok(config_dir()?.join("layouts").join(format!("{}.json", name)))
+}
+
+pub fn save(name: &str, layout: &Layout) -> Result<(), String> {
+    let path = layout_path(name)?;
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir).map_err(|e| e.to_string())?;
+    }
+    let file = File::create(&path).map_err(|e| e.to_string())?;
+    serde_json::to_writer_pretty(file, layout).map_err(|e| e.to_string())
+}
+
+pub fn load(name: &str) -> Result<Layout, String> {
+    let path = layout_path(name)?;
+    let file = File::open(&path).map_err(|e| format!("layout '{}': {}", name, e))?;
+    serde_json::from_reader(file).map_err(|e| format!("layout '{}': {}", name, e))
+}
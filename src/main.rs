@@ -1,32 +1,216 @@
 #![feature(duration_millis_float)]
 
+mod areas;
+mod clipboard;
+mod command;
+mod compare;
+mod export;
+mod follow;
+mod format;
+mod help;
+mod layout;
+mod metrics;
+mod screenshot;
+mod session;
+mod stats;
+mod theme;
 mod trace;
 
 use clap::Parser;
+use command::{Command, InspectTarget, completions, parse_command};
+use format::{Radix, fmt_duration, fmt_id, fmt_millis};
 use ratatui::{
     crossterm::{
         ExecutableCommand,
-        event::{self, Event, KeyCode, KeyEventKind},
+        event::{
+            self, DisableMouseCapture, EnableMouseCapture, Event, KeyCode, KeyEvent, KeyEventKind,
+            KeyModifiers, MouseButton, MouseEvent, MouseEventKind,
+        },
         terminal::{EnterAlternateScreen, LeaveAlternateScreen, disable_raw_mode, enable_raw_mode},
     },
     prelude::*,
-    widgets::{Axis, Block, Chart, Dataset, GraphType, Paragraph},
+    widgets::{Axis, Block, Chart, Clear, Dataset, GraphType, Paragraph, Row, Table},
 };
 use ratatui::{
     style::{Style, Stylize},
     symbols,
 };
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
 use std::io::{self, stdout};
-use trace::{FrameTrace, Trace, read_trace_file};
+use std::time::Duration;
+use trace::{FrameTrace, FrameWindow, SkippedLine, TraceError, parse_duration, read_trace_files};
 
 #[derive(Parser)]
 struct Cli {
+    /// trace file, - reads stdin (which can't be reloaded with :r or followed)
     file: std::path::PathBuf,
+
+    /// second trace file, charted over FILE to compare frame times
+    compare: Option<std::path::PathBuf>,
+
+    /// more trace files of the same session (e.g. rotated logs), appended after FILE in order
+    #[arg(long, value_name = "FILE")]
+    append: Vec<std::path::PathBuf>,
+
+    /// keep reading frames appended to FILE
+    #[arg(long, short)]
+    follow: bool,
+
+    /// how often to check FILE for new frames with --follow
+    #[arg(long, value_name = "DURATION", default_value = "250ms", value_parser = parse_poll_interval)]
+    poll_interval: Duration,
+
+    /// frame time budget in ms
+    #[arg(long, default_value_t = 16.6)]
+    budget: f64,
+
+    /// read only the frame spans up front and the children of frames once a
+    /// command needs them, for captures too large to load in full. FILE is
+    /// read with seeks rather than memory mapped, gzipped files aren't
+    /// supported
+    #[arg(long, alias = "lazy-children", conflicts_with = "follow")]
+    mmap: bool,
+
+    /// name of the span that encloses one frame
+    #[arg(long, value_name = "NAME", default_value = trace::DEFAULT_FRAME_SPAN)]
+    frame_span: String,
+
+    /// leave out the first N frames of FILE
+    #[arg(long, value_name = "N", default_value_t = 0, conflicts_with_all = ["follow", "append"])]
+    skip: usize,
+
+    /// read at most N frames of FILE, after the --skip ones
+    #[arg(long, value_name = "N", conflicts_with_all = ["follow", "append"])]
+    limit: Option<std::num::NonZeroUsize>,
+
+    /// parse FILE on N threads, in chunks of lines
+    #[arg(long, value_name = "N", default_value = "1")]
+    threads: std::num::NonZeroUsize,
+
+    /// restore the view, filters and inspected frame from this file if it
+    /// exists, :w saves them to it
+    #[arg(long, value_name = "FILE")]
+    session: Option<std::path::PathBuf>,
+
+    /// JSON file of the colors to use instead of the default ones, e.g.
+    /// {"total": "blue", "axis": "black"}
+    #[arg(long, value_name = "FILE")]
+    theme: Option<std::path::PathBuf>,
+
+    /// serve frame stats in the Prometheus text format on this port
+    #[arg(long, value_name = "PORT")]
+    metrics_port: Option<u16>,
+
+    /// address to serve --metrics-port on, 0.0.0.0 to reach it from other hosts
+    #[arg(
+        long,
+        value_name = "ADDR",
+        default_value = "127.0.0.1",
+        requires = "metrics_port"
+    )]
+    metrics_addr: std::net::IpAddr,
+}
+
+/// Parses --poll-interval, a zero interval would poll without pause.
+fn parse_poll_interval(s: &str) -> Result<Duration, String> {
+    match parse_duration(s).map_err(|e| e.to_string())? {
+        Duration::ZERO => Err("the poll interval must be greater than zero".to_string()),
+        interval => Ok(interval),
+    }
+}
+
+impl Cli {
+    fn window(&self) -> FrameWindow {
+        FrameWindow {
+            skip: self.skip,
+            limit: self.limit.map(std::num::NonZeroUsize::get),
+        }
+    }
 }
 
 enum InputMode {
     Normal,
     Editing,
+    /// editing the note of the inspected frame
+    Note,
+}
+
+const DEFAULT_COMPLIANCE_WINDOW: usize = 60;
+const DEFAULT_HISTOGRAM_BINS: usize = 30;
+/// Frames listed by :top without a number.
+const DEFAULT_TOP_FRAMES: usize = 10;
+/// standard deviations above the mean for a frame to count as a spike
+const DEFAULT_SPIKE_STDDEVS: f64 = 3.0;
+/// lines scrolled in the detail panel with PageUp/PageDown
+const DETAIL_SCROLL_STEP: i16 = 10;
+/// width of the frame window when centering on a frame without a window set
+const DEFAULT_WINDOW: usize = 100;
+/// idle fraction of a frame above which :idle-warn flags it
+const DEFAULT_IDLE_WARN: f64 = 0.7;
+/// minimum number of frames on either side of a detected regression
+const REGRESSION_MIN_SEGMENT: usize = 30;
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum Scale {
+    Log10,
+    Log2,
+    Linear,
+    /// linear bars with an additional log axis on the right
+    Dual,
+}
+
+impl Scale {
+    fn apply(self, millis: f64) -> f64 {
+        match self {
+            Scale::Log10 => millis.log10(),
+            Scale::Log2 => millis.log2(),
+            Scale::Linear | Scale::Dual => millis,
+        }
+    }
+
+    fn title(self) -> &'static str {
+        match self {
+            Scale::Log10 => "ms (log10 scale)",
+            Scale::Log2 => "ms (log2 scale)",
+            Scale::Linear => "ms (linear scale)",
+            Scale::Dual => "ms (linear, log on the right)",
+        }
+    }
+}
+
+/// What the chart plots.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum View {
+    /// total duration of the frame span
+    Frames,
+    /// time since the previous frame's timestamp
+    Interval,
+    /// frame time stacked by child target
+    Areas,
+    /// number of frames per frame duration range
+    Histogram,
+    /// frame duration over wall-clock time since the first frame
+    Wallclock,
+    /// fraction of frames up to each frame duration
+    Cdf,
+    /// busy and idle part of each frame stacked
+    Stacked,
+}
+
+/// What the detail panel shows.
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum DetailMode {
+    /// children of the inspected frame
+    List,
+    /// one row per frame of the frame window
+    Table,
+    /// time of the inspected frame per child target, as proportional bars
+    Donut,
+    /// children of the inspected frame placed by their time within it
+    Timeline,
 }
 
 struct FrameState {
@@ -34,72 +218,919 @@ struct FrameState {
     end: usize,
 }
 
+/// Order of the children in the detail panel.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+enum ChildOrder {
+    /// longest first
+    #[default]
+    Duration,
+    /// by target, then span name
+    Name,
+    /// as in the trace file
+    Original,
+}
+
 struct DetailState {
-    frame_trace: FrameTrace,
+    /// index into `trace_data`
+    index: usize,
+    /// indices of the frame's children, in `order`
+    children: Vec<usize>,
+    order: ChildOrder,
+}
+
+impl DetailState {
+    fn new(index: usize, frame_trace: &FrameTrace, order: ChildOrder) -> DetailState {
+        let child_traces = &frame_trace.child_traces;
+        let mut children: Vec<usize> = (0..child_traces.len()).collect();
+        match order {
+            ChildOrder::Duration => {
+                children.sort_by_key(|i| std::cmp::Reverse(child_traces[*i].total_duration()))
+            }
+            ChildOrder::Name => children.sort_by_key(|i| {
+                let child = &child_traces[*i];
+                (&child.target, &child.span.name)
+            }),
+            ChildOrder::Original => {}
+        }
+        DetailState {
+            index,
+            children,
+            order,
+        }
+    }
+}
+
+/// Frames drawn in a distinct color on top of the regular bars.
+struct Highlight {
+    label: String,
+    color: Color,
+    /// indices into `trace_data`
+    frames: Vec<usize>,
+}
+
+/// Text output of an analysis command, shown in place of the frame detail.
+struct Report {
+    title: String,
+    text: String,
+    kind: ReportKind,
+}
+
+/// What a [`Report`] is a list of, whether its rows can be picked.
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum ReportKind {
+    /// plain text
+    Text,
+    /// the :agg target report, targets are picked from it
+    TargetAgg,
+    /// the :top report, frames are picked from it
+    TopFrames,
+}
+
+/// Frames found with :search, stepped through with n/N.
+struct Search {
+    /// indices of the matching frames
+    matches: Vec<usize>,
+    /// position in `matches` of the inspected match
+    current: Option<usize>,
+}
+
+/// Time spent in the children of one target, summed over frames.
+struct TargetAgg {
+    target: String,
+    total: Duration,
+    /// fraction of the summed frame time
+    share: f64,
+    /// number of child spans
+    calls: usize,
+}
+
+/// Summary of the frame durations in the frame window, in ms.
+#[derive(Serialize)]
+struct FrameStats {
+    count: usize,
+    min: f64,
+    max: f64,
+    mean: f64,
+    p50: f64,
+    p95: f64,
+    p99: f64,
+    /// frames over the mean plus `DEFAULT_SPIKE_STDDEVS` standard deviations
+    spikes: usize,
 }
 
+/// How evenly paced the frames in the frame window are, independent of how
+/// long they take on average.
+#[derive(Default)]
+struct PacingStats {
+    /// consecutive frames compared
+    deltas: usize,
+    /// standard deviation of the change in duration from one frame to the
+    /// next, in ms
+    delta_stddev: f64,
+    /// share of frames changing by more than `JANK_BUDGET_SHARE` of the
+    /// budget from the frame before
+    jank: f64,
+}
+
+/// Change in duration from the previous frame, as a share of the budget,
+/// that counts as jank.
+const JANK_BUDGET_SHARE: f64 = 0.25;
+
 struct State {
+    /// the loaded trace files, FILE and the --append ones
+    files: Vec<std::path::PathBuf>,
+    /// why the trace files couldn't be read, shown until :r reads them
+    load_error: Option<String>,
+    /// where :w saves the session without a path, --session
+    session: Option<std::path::PathBuf>,
+    /// whether the children of frames are read on demand, --mmap
+    mmap: bool,
+    /// name of the span that encloses one frame, --frame-span
+    frame_span: String,
+    /// frames of the trace file read, --skip and --limit
+    window: FrameWindow,
+    /// threads parsing a trace file, --threads
+    threads: usize,
+    /// frames as loaded from the trace file
+    source_frames: Vec<FrameTrace>,
+    /// number of source frames combined into each charted frame
+    merge: usize,
+    /// inclusive ranges of source frame ids left out with :exclude
+    excluded: Vec<(u64, u64)>,
+    /// only frames and children at this level are shown, set with :level
+    level: Option<String>,
+    /// only frames taking `min..=max` ms are charted, set with :filter
+    duration_filter: Option<(f64, Option<f64>)>,
+    /// maximum of the charted series in ms
     max: f64,
+    median: f64,
     trace_data: Vec<FrameTrace>,
+    /// (frame id, duration in ms) per frame, untransformed
+    millis: Vec<(f64, f64)>,
+    /// (frame id, ms since the previous frame) for frames with timestamps
+    intervals: Vec<(f64, f64)>,
+    /// (frame id, duration in ms) per frame of the compared file, untransformed
+    compare_millis: Vec<(f64, f64)>,
+    /// `compare_millis` in the chart scale
+    compare_data: Vec<(f64, f64)>,
+    /// file name of the compared file
+    compare_label: String,
+    /// bar color per frame target, in order of first appearance
+    target_colors: HashMap<String, Color>,
+    /// ms from the start of the capture to the end of each frame
+    elapsed: Vec<f64>,
+    /// (seconds since the first frame, duration in ms) per frame
+    wallclock: Vec<(f64, f64)>,
+    /// seconds between the first and last frame timestamp
+    capture_secs: Option<f64>,
+    /// per-target time of every frame, built when the areas view is opened
+    target_areas: Vec<areas::Band>,
+    /// missing frame ids marked on the chart, set with :gaps
+    gaps: Vec<(u64, u64)>,
+    /// target charted instead of the frame duration, picked in the
+    /// :agg target report
+    drilldown_target: Option<String>,
+    /// ms per frame spent in children of `drilldown_target`
+    drilldown: Vec<(f64, f64)>,
+    /// row of the :agg target report picked with Up/Down
+    target_pick: usize,
+    /// frames listed by :top and the row picked with Up/Down
+    top_frames: usize,
+    top_pick: usize,
+    /// the series of the active view with the scale applied, fed to the chart
     data: Vec<(f64, f64)>,
+    scale: Scale,
+    view: View,
+    /// number of bins of the histogram view
+    histogram_bins: usize,
 
     input: String,
     input_mode: InputMode,
     character_index: usize,
+    /// executed commands, oldest first
+    history: Vec<String>,
+    /// entry of `history` recalled into the input with Up/Down
+    history_index: Option<usize>,
+    /// candidates Tab cycles through and the one in the input
+    completions: Vec<String>,
+    completion_index: usize,
 
     frame_state: Option<FrameState>,
+    /// fit the y axis to the frames in the window
+    zoom_y: bool,
     detail_state: Option<DetailState>,
+    report: Option<Report>,
+    detail_mode: DetailMode,
+    highlight: Option<Highlight>,
+    search: Option<Search>,
+    /// index of the frame selected with j/k
+    selected_frame: Option<usize>,
+    /// whether the help overlay is shown
+    show_help: bool,
+    /// whether anything changed since the last draw
+    dirty: bool,
+    /// where the bars of the last drawn chart are, none in views without
+    /// frames on the x axis
+    chart_graph: Option<Rect>,
+    /// mouse position over the chart, a tooltip shows the frame under it
+    hover: Option<(u16, u16)>,
+    /// lines scrolled down in the frame detail or report
+    detail_scroll: u16,
+    /// whether `g` was pressed, a second one jumps to the first frame
+    pending_g: bool,
+
+    /// bookmark notes keyed by frame id
+    notes: HashMap<u64, String>,
+
+    /// frame time budget in ms
+    budget: f64,
+    /// trailing window of the budget compliance overlay, if shown
+    compliance_window: Option<usize>,
+    /// whether the frames per second overlay is shown
+    show_fps: bool,
+    /// whether grid lines are drawn behind the bars, :grid
+    show_grid: bool,
+    /// window of the moving average overlay, if shown
+    average_window: Option<usize>,
+
+    /// result or error of the last command, shown in the command line
+    message: Option<String>,
+
+    /// digits after the decimal point when displaying durations
+    precision: usize,
+
+    theme: theme::Theme,
+    /// base of displayed frame ids
+    radix: Radix,
 }
 
 struct App {
     state: State,
+    /// what was drawn to the terminal last
+    last_frame: Buffer,
+    /// whether FILE is followed, --follow, also while it can't be opened
+    follow: bool,
+    /// source of new frames with --follow, none until FILE is opened
+    follower: Option<follow::Follower>,
+    poll_interval: Duration,
+    /// endpoint of --metrics-port
+    metrics: Option<metrics::Metrics>,
 }
 
 fn main() -> Result<(), String> {
     let args = Cli::parse();
 
-    let trace_data = read_trace_file(&args.file)?;
+    let theme = match &args.theme {
+        Some(path) => theme::Theme::load(path)?,
+        None => theme::Theme::default(),
+    };
+    let metrics = args
+        .metrics_port
+        .map(|port| metrics::Metrics::serve(args.metrics_addr, port))
+        .transpose()?;
+
+    let mut follower = None;
+    let mut skipped = Vec::new();
+    if args.follow && args.file == std::path::Path::new(trace::STDIN_PATH) {
+        return Err("can't follow stdin".to_string());
+    }
+    // unreadable traces are shown in the TUI, it starts with an empty chart
+    let read = if args.follow {
+        follow::Follower::open(&args.file, &args.frame_span).and_then(|(f, polled)| {
+            follower = Some(f);
+            skipped = polled.skipped;
+            polled.error.map_or(Ok(polled.frames), Err)
+        })
+    } else {
+        let mut paths = vec![args.file.clone()];
+        paths.extend(args.append.iter().cloned());
+        read_trace_files(
+            &paths,
+            args.mmap,
+            &args.frame_span,
+            args.window(),
+            args.threads.get(),
+        )
+        .map(|(trace_data, skipped_lines)| {
+            skipped = skipped_lines;
+            trace_data
+        })
+    };
+    let (trace_data, load_error) = match read {
+        Ok(trace_data) => (trace_data, None),
+        Err(e) if args.follow => (Vec::new(), Some(not_following(&e))),
+        Err(e) => (Vec::new(), Some(e.to_string())),
+    };
+
+    let mut app = App::new(trace_data, &args);
+    app.state.theme = theme;
+    app.state.load_error = load_error;
+    if let Some(path) = &args.compare {
+        match trace::read_trace_file(
+            path,
+            &args.frame_span,
+            FrameWindow::default(),
+            args.threads.get(),
+        ) {
+            Ok((frames, _)) => app.set_compare_frames(path, &frames),
+            Err(e) => app.state.message = Some(e.to_string()),
+        }
+    }
+    app.follower = follower;
+    app.metrics = metrics;
+    app.publish_metrics();
+    if let Some(path) = &args.session
+        && path.exists()
+    {
+        match session::load(path) {
+            Ok(session) => app.apply_session(session),
+            Err(e) => app.state.message = Some(e),
+        }
+    }
+    if let Some(message) = skipped_message(&skipped) {
+        app.state.message = Some(message);
+    }
 
     enable_raw_mode().map_err(|e| e.to_string())?;
     stdout()
         .execute(EnterAlternateScreen)
+        .and_then(|stdout| stdout.execute(EnableMouseCapture))
         .map_err(|e| e.to_string())?;
-    let terminal = Terminal::new(CrosstermBackend::new(stdout())).map_err(|e| e.to_string())?;
-
-    App::new(trace_data)
-        .run(terminal)
-        .map_err(|e| e.to_string())?;
+    // leave the alternate screen even if the TUI fails
+    let result =
+        Terminal::new(CrosstermBackend::new(stdout())).and_then(|terminal| app.run(terminal));
 
     disable_raw_mode().map_err(|e| e.to_string())?;
     stdout()
-        .execute(LeaveAlternateScreen)
+        .execute(DisableMouseCapture)
+        .and_then(|stdout| stdout.execute(LeaveAlternateScreen))
         .map_err(|e| e.to_string())?;
-    Ok(())
+    result.map_err(|e| e.to_string())
 }
 
-impl App {
-    fn new(trace_data: Vec<FrameTrace>) -> App {
-        let mut data = Vec::with_capacity(trace_data.len());
-        let mut max: f64 = 0.0;
-        for frame_trace in &trace_data {
-            let duration = frame_trace.trace.total_duration();
-            let millis = duration.as_millis_f64();
-            max = max.max(millis);
-            data.push((frame_trace.trace.span.id.unwrap() as f64, millis.log10()));
-        }
+/// The error of a followed file that couldn't be opened, it is opened once it
+/// can be.
+fn not_following(e: &TraceError) -> String {
+    format!("{}, following is inactive until it can be opened", e)
+}
+
+/// The message telling how many lines weren't valid traces, none if all were.
+fn skipped_message(skipped: &[SkippedLine]) -> Option<String> {
+    let (line, e) = skipped.first()?;
+    Some(format!(
+        "{} malformed lines skipped, first at line {}: {}",
+        skipped.len(),
+        line,
+        e
+    ))
+}
 
-        App {
+impl App {
+    fn new(trace_data: Vec<FrameTrace>, args: &Cli) -> App {
+        let mut app = App {
+            last_frame: Buffer::empty(Rect::default()),
+            follow: args.follow,
+            follower: None,
+            poll_interval: args.poll_interval,
+            metrics: None,
             state: State {
-                trace_data,
-                data,
-                max,
+                files: std::iter::once(&args.file)
+                    .chain(&args.append)
+                    .cloned()
+                    .collect(),
+                load_error: None,
+                session: args.session.clone(),
+                mmap: args.mmap,
+                frame_span: args.frame_span.clone(),
+                window: args.window(),
+                threads: args.threads.get(),
+                source_frames: Vec::new(),
+                merge: 1,
+                excluded: Vec::new(),
+                level: None,
+                duration_filter: None,
+                trace_data: Vec::new(),
+                millis: Vec::new(),
+                intervals: Vec::new(),
+                compare_millis: Vec::new(),
+                compare_data: Vec::new(),
+                compare_label: String::new(),
+                target_colors: HashMap::new(),
+                elapsed: Vec::new(),
+                wallclock: Vec::new(),
+                capture_secs: None,
+                target_areas: Vec::new(),
+                drilldown_target: None,
+                gaps: Vec::new(),
+                drilldown: Vec::new(),
+                target_pick: 0,
+                top_frames: DEFAULT_TOP_FRAMES,
+                top_pick: 0,
+                data: Vec::new(),
+                scale: Scale::Log10,
+                view: View::Frames,
+                histogram_bins: DEFAULT_HISTOGRAM_BINS,
+                max: 0.0,
+                median: 0.0,
                 input: String::new(),
                 input_mode: InputMode::Normal,
                 character_index: 0,
+                history: Vec::new(),
+                history_index: None,
+                completions: Vec::new(),
+                completion_index: 0,
                 frame_state: None,
+                zoom_y: false,
                 detail_state: None,
+                report: None,
+                detail_mode: DetailMode::List,
+                highlight: None,
+                search: None,
+                selected_frame: None,
+                show_help: false,
+                dirty: true,
+                chart_graph: None,
+                hover: None,
+                detail_scroll: 0,
+                pending_g: false,
+                notes: HashMap::new(),
+                budget: args.budget,
+                compliance_window: None,
+                show_fps: false,
+                show_grid: false,
+                average_window: None,
+                message: None,
+                precision: format::DEFAULT_PRECISION,
+                theme: theme::Theme::default(),
+                radix: Radix::Dec,
             },
+        };
+        app.set_frames(trace_data.clone());
+        app.state.source_frames = trace_data;
+        app
+    }
+
+    /// Replaces the charted frames and recomputes everything derived from them.
+    fn set_frames(&mut self, trace_data: Vec<FrameTrace>) {
+        self.state.trace_data = trace_data;
+        // indices into the old frames are meaningless now
+        self.state.detail_state = None;
+        self.state.highlight = None;
+        self.state.search = None;
+        self.state.selected_frame = None;
+        self.state.frame_state = None;
+        self.recompute_frames();
+    }
+
+    /// Recomputes the per-frame series and statistics from `trace_data`.
+    fn recompute_frames(&mut self) {
+        let trace_data = &self.state.trace_data;
+        let mut frame_millis = Vec::with_capacity(trace_data.len());
+        let mut sorted = Vec::with_capacity(trace_data.len());
+        for (i, frame_trace) in trace_data.iter().enumerate() {
+            let duration = frame_trace.trace.total_duration();
+            let millis = duration.as_millis_f64();
+            sorted.push(millis);
+            frame_millis.push((frame_trace.id(i) as f64, millis));
+        }
+        sorted.sort_by(|a, b| a.partial_cmp(b).unwrap());
+        let median = stats::median(&sorted);
+
+        // prefer wall-clock time, frame durations don't include the time between frames
+        let first = trace_data.first();
+        let first_timestamp = first.and_then(|f| f.trace.timestamp);
+        let first_millis = first.map_or(0.0, |f| f.trace.total_duration().as_millis_f64());
+        let mut elapsed = Vec::with_capacity(trace_data.len());
+        let mut wallclock = Vec::with_capacity(trace_data.len());
+        let mut sum = 0.0;
+        for frame_trace in trace_data {
+            let millis = frame_trace.trace.total_duration().as_millis_f64();
+            sum += millis;
+            let end = match (first_timestamp, frame_trace.trace.timestamp) {
+                (Some(first_ts), Some(ts)) => {
+                    (ts - first_ts).num_microseconds().unwrap_or(0) as f64 / 1000.0 + first_millis
+                }
+                _ => sum,
+            };
+            elapsed.push(end);
+            wallclock.push(((end - first_millis) / 1000.0, millis));
+        }
+
+        let mut intervals = Vec::new();
+        for (i, (prev, frame_trace)) in trace_data.iter().zip(trace_data.iter().skip(1)).enumerate()
+        {
+            if let (Some(prev_ts), Some(ts)) = (prev.trace.timestamp, frame_trace.trace.timestamp) {
+                let delta = (ts - prev_ts).num_microseconds().unwrap_or(0) as f64 / 1000.0;
+                intervals.push((frame_trace.id(i + 1) as f64, delta));
+            }
+        }
+
+        // new targets get the next color, known ones keep theirs
+        for frame_trace in &self.state.trace_data {
+            let next = self.state.target_colors.len();
+            self.state
+                .target_colors
+                .entry(frame_trace.trace.target.clone())
+                .or_insert(self.state.theme.palette_color(next));
+        }
+
+        self.state.millis = frame_millis;
+        self.state.median = median;
+        self.state.elapsed = elapsed;
+        self.state.wallclock = wallclock;
+        self.state.capture_secs = match (first_timestamp, trace_data.last()) {
+            (Some(first_ts), Some(last)) => last
+                .trace
+                .timestamp
+                .and_then(|ts| (ts - first_ts).num_microseconds())
+                .map(|micros| micros as f64 / 1e6),
+            _ => None,
+        };
+        self.state.intervals = intervals;
+        self.state.target_areas = Vec::new();
+        if let Some(target) = &self.state.drilldown_target {
+            self.state.drilldown = self.target_series(target);
+        }
+        self.set_view(self.state.view);
+    }
+
+    /// Adds newly read frames, keeping the current view.
+    fn append_frames(&mut self, frames: Vec<FrameTrace>) {
+        let first = self.state.source_frames.len();
+        self.state.source_frames.extend(frames.iter().cloned());
+        if self.state.merge == 1 {
+            let frames = (first..)
+                .zip(frames)
+                .filter(|(i, f)| self.is_active(*i, f))
+                .map(|(_, f)| f);
+            self.state.trace_data.extend(frames.collect::<Vec<_>>());
+        } else {
+            self.state.trace_data = self.active_frames();
+        }
+        self.recompute_frames();
+        self.publish_metrics();
+    }
+
+    /// Reads the trace files again, keeping the view and merging but not the
+    /// frame window and inspected frame.
+    fn exec_reload(&mut self) {
+        if self.state.files[0] == std::path::Path::new(trace::STDIN_PATH) {
+            self.state.message = Some("can't reload stdin".to_string());
+            return;
+        }
+        let frames = if self.follow {
+            self.open_follower()
+        } else {
+            read_trace_files(
+                &self.state.files,
+                self.state.mmap,
+                &self.state.frame_span,
+                self.state.window,
+                self.state.threads,
+            )
+        };
+        match frames {
+            Ok((frames, skipped)) => {
+                self.state.source_frames = frames;
+                self.set_frames(self.active_frames());
+                self.publish_metrics();
+                let mut message = format!("reloaded {} frames", self.state.source_frames.len());
+                if !skipped.is_empty() {
+                    message.push_str(&format!(", {} malformed lines skipped", skipped.len()));
+                }
+                self.state.message = Some(message);
+            }
+            Err(e) => {
+                self.state.load_error = Some(match &self.follower {
+                    None if self.follow => not_following(&e),
+                    _ => e.to_string(),
+                });
+                self.state.message = None;
+                return;
+            }
+        }
+        self.state.load_error = None;
+    }
+
+    /// Opens the followed file from its start, following it from then on.
+    fn open_follower(&mut self) -> Result<(Vec<FrameTrace>, Vec<SkippedLine>), TraceError> {
+        let (follower, polled) =
+            follow::Follower::open(&self.state.files[0], &self.state.frame_span)?;
+        self.follower = Some(follower);
+        polled
+            .error
+            .map_or(Ok((polled.frames, polled.skipped)), Err)
+    }
+
+    /// Updates the `--metrics-port` endpoint with the stats of all frames
+    /// read so far, regardless of merging.
+    fn publish_metrics(&self) {
+        if let Some(metrics) = &self.metrics {
+            let millis: Vec<f64> = self
+                .state
+                .source_frames
+                .iter()
+                .map(|f| f.trace.total_duration().as_millis_f64())
+                .collect();
+            metrics.update(&millis, self.state.budget);
+        }
+    }
+
+    /// Charts every `k` consecutive frames as one, `k == 1` restores the
+    /// original frames.
+    fn set_merge(&mut self, k: usize) {
+        self.state.merge = k;
+        self.set_frames(self.active_frames());
+    }
+
+    /// Whether the source frame at `index` has an id `:exclude`d, the id
+    /// falls back to the index like on the chart.
+    fn is_excluded(&self, index: usize, frame: &FrameTrace) -> bool {
+        let id = frame.id(index);
+        self.state
+            .excluded
+            .iter()
+            .any(|(lower, upper)| (*lower..=*upper).contains(&id))
+    }
+
+    /// Whether `trace` is at the level picked with `:level`, always true
+    /// without one.
+    fn level_matches(&self, trace: &trace::Trace) -> bool {
+        self.state
+            .level
+            .as_ref()
+            .is_none_or(|level| trace.level.eq_ignore_ascii_case(level))
+    }
+
+    /// Whether the duration of `frame` is in the `:filter` range, always true
+    /// without one.
+    fn in_duration_filter(&self, frame: &FrameTrace) -> bool {
+        self.state.duration_filter.is_none_or(|(min, max)| {
+            let ms = frame.trace.total_duration().as_millis_f64();
+            ms >= min && max.is_none_or(|max| ms <= max)
+        })
+    }
+
+    /// Whether the source frame at `index` isn't excluded, is in the
+    /// `:filter` range and has an event at the `:level` level.
+    fn is_active(&self, index: usize, frame: &FrameTrace) -> bool {
+        !self.is_excluded(index, frame)
+            && self.in_duration_filter(frame)
+            && (self.level_matches(&frame.trace)
+                || frame.child_traces.iter().any(|c| self.level_matches(c)))
+    }
+
+    /// The source frames without the excluded ones, those outside the
+    /// `:filter` range and those without an event at the `:level` level,
+    /// merged.
+    fn active_frames(&self) -> Vec<FrameTrace> {
+        let frames: Vec<FrameTrace> = self
+            .state
+            .source_frames
+            .iter()
+            .enumerate()
+            .filter(|(i, f)| self.is_active(*i, f))
+            .map(|(_, f)| f.clone())
+            .collect();
+        if self.state.merge == 1 {
+            frames
+        } else {
+            trace::merge_frames(&frames, self.state.merge)
+        }
+    }
+
+    /// Leaves the frames with ids in `range` (both included) out of the
+    /// charted frames and their stats, `None` brings all back.
+    fn exec_exclude(&mut self, range: Option<(u64, u64)>) {
+        match range {
+            Some(range) => self.state.excluded.push(range),
+            None => self.state.excluded.clear(),
+        }
+        self.set_frames(self.active_frames());
+        let excluded = self
+            .state
+            .source_frames
+            .iter()
+            .enumerate()
+            .filter(|(i, f)| self.is_excluded(*i, f))
+            .count();
+        self.state.message = Some(format!("{} frames excluded", excluded));
+    }
+
+    /// Charts only the frames with an event at `level` and lists only the
+    /// children at that level, `None` shows every level again.
+    fn exec_level(&mut self, level: Option<String>) {
+        self.read_all_children();
+        self.state.level = level;
+        self.set_frames(self.active_frames());
+        self.state.message = Some(format!("{} frames shown", self.state.trace_data.len()));
+    }
+
+    /// Charts only the frames taking `min..=max` ms, `None` charts all again.
+    fn exec_filter(&mut self, filter: Option<(f64, Option<f64>)>) {
+        self.state.duration_filter = filter;
+        self.set_frames(self.active_frames());
+        self.state.message = Some(format!("{} frames shown", self.state.trace_data.len()));
+    }
+
+    /// The untransformed series of the active view.
+    fn series(&self) -> &[(f64, f64)] {
+        match self.state.view {
+            View::Frames if self.state.drilldown_target.is_some() => &self.state.drilldown,
+            View::Frames | View::Areas | View::Stacked | View::Histogram | View::Cdf => {
+                &self.state.millis
+            }
+            View::Interval => &self.state.intervals,
+            View::Wallclock => &self.state.wallclock,
+        }
+    }
+
+    /// Charted frame id range, the frame window if set.
+    fn x_bounds(&self) -> (f64, f64) {
+        match &self.state.frame_state {
+            Some(frame_bounds) => (frame_bounds.start as f64, frame_bounds.end as f64),
+            // ids can have gaps once frames are filtered out
+            None => {
+                let last_id = self.state.millis.last().map_or(0.0, |(id, _)| *id);
+                (0.0, last_id.max(self.state.data.len() as f64))
+            }
+        }
+    }
+
+    /// Charted x range, the frame window converted to seconds in the
+    /// wall-clock view.
+    fn chart_x_bounds(&self) -> (f64, f64) {
+        let (start, end) = self.x_bounds();
+        if self.state.view != View::Wallclock || self.state.wallclock.is_empty() {
+            return (start, end);
+        }
+        let last = self.state.wallclock.len() - 1;
+        let x = |frame: f64| self.state.wallclock[(frame.max(0.0) as usize).min(last)].0;
+        (x(start), x(end))
+    }
+
+    /// Position of a frame on the x axis of the chart.
+    fn chart_x(&self, index: usize) -> f64 {
+        match self.state.view {
+            View::Wallclock => self.state.wallclock[index].0,
+            _ => self.state.millis[index].0,
+        }
+    }
+
+    /// Value in ms at the top of the y axis: the maximum of the series, or
+    /// of only the frames in the window with `:zoom-to-selection`.
+    fn y_axis_max(&self) -> f64 {
+        if !self.state.zoom_y {
+            return self.state.max;
         }
+        let (start, end) = self.chart_x_bounds();
+        self.series()
+            .iter()
+            .chain(self.compare_series())
+            .filter(|(x, _)| *x >= start && *x <= end)
+            .fold(0.0, |max, (_, ms)| ms.max(max))
+    }
+
+    /// The untransformed series of the compared file, if charted in the
+    /// active view.
+    fn compare_series(&self) -> &[(f64, f64)] {
+        match self.state.view {
+            View::Frames => &self.state.compare_millis,
+            _ => &[],
+        }
+    }
+
+    /// Charts the frames of a second file over the loaded ones.
+    fn set_compare_frames(&mut self, path: &std::path::Path, frames: &[FrameTrace]) {
+        self.state.compare_millis = frames
+            .iter()
+            .enumerate()
+            .map(|(i, f)| (f.id(i) as f64, f.trace.total_duration().as_millis_f64()))
+            .collect();
+        self.state.compare_label = path.file_name().map_or(path.display().to_string(), |name| {
+            name.to_string_lossy().into_owned()
+        });
+        self.rebuild_data();
+    }
+
+    fn rebuild_data(&mut self) {
+        let scale = self.state.scale;
+        let scaled = |series: &[(f64, f64)]| -> Vec<(f64, f64)> {
+            series
+                .iter()
+                .map(|(x, millis)| (*x, scale.apply(*millis)))
+                .collect()
+        };
+        let series = self.series();
+        let compare = self.compare_series();
+        // same y axis for both files
+        let max = (series.iter().chain(compare)).fold(0.0, |max, (_, ms)| ms.max(max));
+        let data = scaled(series);
+        let compare_data = scaled(compare);
+        self.state.max = max;
+        self.state.data = data;
+        self.state.compare_data = compare_data;
+    }
+
+    /// Whether `view` needs frame timestamps the trace doesn't have.
+    fn lacks_timestamps(&self, view: View) -> bool {
+        match view {
+            View::Interval => self.state.intervals.is_empty(),
+            View::Wallclock => self
+                .state
+                .trace_data
+                .iter()
+                .all(|f| f.trace.timestamp.is_none()),
+            _ => false,
+        }
+    }
+
+    fn set_view(&mut self, view: View) {
+        self.state.view = view;
+        if view == View::Areas && self.state.target_areas.is_empty() {
+            self.read_all_children();
+            self.state.target_areas = self.build_target_areas();
+        }
+        self.rebuild_data();
+    }
+
+    /// Switches between charting frames by index and by wall-clock time.
+    fn exec_timeline(&mut self) {
+        if self.state.view == View::Wallclock {
+            self.set_view(View::Frames);
+        } else if self.lacks_timestamps(View::Wallclock) {
+            self.state.message = Some("no frame timestamps in trace".to_string());
+        } else {
+            self.set_view(View::Wallclock);
+        }
+    }
+
+    /// One band per child target, largest overall first, plus a band for the
+    /// frame time not covered by any child.
+    fn build_target_areas(&self) -> Vec<areas::Band> {
+        let frames = self.state.trace_data.len();
+        let mut per_target: HashMap<&str, Vec<f64>> = HashMap::new();
+        let mut own = Vec::with_capacity(frames);
+        for (i, frame_trace) in self.state.trace_data.iter().enumerate() {
+            let mut children = 0.0;
+            for child in &frame_trace.child_traces {
+                let millis = child.total_duration().as_millis_f64();
+                per_target
+                    .entry(child.target.as_str())
+                    .or_insert_with(|| vec![0.0; frames])[i] += millis;
+                children += millis;
+            }
+            own.push((frame_trace.trace.total_duration().as_millis_f64() - children).max(0.0));
+        }
+
+        let mut targets: Vec<(&str, Vec<f64>)> = per_target.into_iter().collect();
+        targets.sort_by(|a, b| {
+            let a_sum: f64 = a.1.iter().sum();
+            let b_sum: f64 = b.1.iter().sum();
+            b_sum.partial_cmp(&a_sum).unwrap()
+        });
+        targets.push(("(self)", own));
+        targets
+            .into_iter()
+            .enumerate()
+            .map(|(i, (name, values))| areas::Band {
+                name: name.to_string(),
+                color: self.state.theme.palette_color(i),
+                values,
+            })
+            .collect()
+    }
+
+    /// Busy and idle time of every frame.
+    fn busy_idle_bands(&self) -> Vec<areas::Band> {
+        let (busy, idle) = self
+            .state
+            .trace_data
+            .iter()
+            .map(|f| {
+                (
+                    f.trace.busy().as_millis_f64(),
+                    f.trace.idle().as_millis_f64(),
+                )
+            })
+            .unzip();
+        vec![
+            areas::Band {
+                name: "busy".to_string(),
+                color: self.state.theme.busy,
+                values: busy,
+            },
+            areas::Band {
+                name: "idle".to_string(),
+                color: self.state.theme.idle,
+                values: idle,
+            },
+        ]
+    }
+
+    fn set_scale(&mut self, scale: Scale) {
+        self.state.scale = scale;
+        self.rebuild_data();
     }
 
     fn move_cursor_left(&mut self) {
@@ -146,33 +1177,23 @@ impl App {
     }
 
     fn exec_command(&mut self) -> bool {
-        if self.state.input == ":q" {
+        let input_cmd = self.state.input.clone();
+        let command = parse_command(&input_cmd);
+        if command == Ok(Command::Quit) {
             return true;
         }
 
-        let input_cmd = self.state.input.clone();
+        self.state.message = None;
+        self.state.history_index = None;
+        if !input_cmd.trim().is_empty() && self.state.history.last() != Some(&input_cmd) {
+            self.state.history.push(input_cmd.clone());
+        }
 
-        // frame commands
-        if self.state.input.starts_with(":f") {
-            let mut iter = input_cmd.split_whitespace();
-            iter.next();
-            if let Some(str) = iter.next() {
-                if str == "all" {
-                    self.state.frame_state = None
-                } else if str == "inspect" {
-                    self.exec_frame_inspect(iter.next());
-                } else {
-                    let s: Vec<&str> = str.split("..").collect();
-                    if s.len() == 2 {
-                        let lower: usize = s[0].parse().unwrap();
-                        let upper: usize = s[1].parse().unwrap();
-                        self.state.frame_state = Some(FrameState {
-                            start: lower,
-                            end: upper,
-                        });
-                    }
-                }
-            }
+        match command {
+            Ok(command) => self.dispatch(command),
+            // a lone `:` does nothing
+            Err(_) if input_cmd.trim().trim_start_matches(':').is_empty() => {}
+            Err(e) => self.state.message = Some(e.to_string()),
         }
 
         self.state.input.clear();
@@ -181,156 +1202,2712 @@ impl App {
         false
     }
 
-    fn exec_frame_inspect(&mut self, cmd: Option<&str>) {
-        if let Some("max") = cmd {
-            let mut max: f64 = 0.0;
-            //let mut max_frame_id = 0;
-            let mut max_trace = &self.state.trace_data[0];
-            for frame_trace in &self.state.trace_data {
-                let duration = frame_trace.trace.total_duration();
-                let millis = duration.as_millis_f64();
-                if millis > max {
-                    max = millis;
-                    //max_frame_id = trace.span.id.unwrap();
-                    max_trace = frame_trace;
+    fn dispatch(&mut self, command: Command) {
+        match command {
+            Command::Quit => {}
+            Command::FrameAll => self.state.frame_state = None,
+            Command::FrameRange { start, end } => {
+                self.state.frame_state = Some(FrameState { start, end })
+            }
+            Command::Inspect(target) => self.exec_frame_inspect(target),
+            Command::FrameGoto(id) => self.exec_frame_goto(id),
+            Command::FrameDiff(first, second) => self.exec_frame_diff(first, second),
+            Command::Exclude { lower, upper } => self.exec_exclude(Some((lower, upper))),
+            Command::ExcludeOff => self.exec_exclude(None),
+            Command::Filter { min, max } => self.exec_filter(Some((min, max))),
+            Command::FilterClear => self.exec_filter(None),
+            Command::Write(path) => self.exec_write(path),
+            Command::Mark { frame_id, note } => self.set_note(frame_id, &note),
+            Command::Compliance(window) => self.state.compliance_window = window,
+            Command::Fps => self.exec_fps(),
+            Command::Grid => self.state.show_grid = !self.state.show_grid,
+            Command::Average(window) => {
+                self.state.average_window =
+                    window.map(|window| window.min(self.state.millis.len()).max(1))
+            }
+            Command::LayoutSave(name) => {
+                self.state.message = Some(match layout::save(&name, &self.layout()) {
+                    Ok(()) => format!("saved layout '{}'", name),
+                    Err(e) => e,
+                });
+            }
+            Command::LayoutLoad(name) => match layout::load(&name) {
+                Ok(layout) => {
+                    let stale = self.apply_layout(layout);
+                    self.state.message = Some(if stale.is_empty() {
+                        format!("loaded layout '{}'", name)
+                    } else {
+                        format!(
+                            "loaded layout '{}' without {}, not in the trace",
+                            name,
+                            stale.join(" and ")
+                        )
+                    });
                 }
+                Err(e) => self.state.message = Some(e),
+            },
+            Command::ExportThumb {
+                path,
+                width,
+                height,
+            } => self.exec_export_thumb(&path, width, height),
+            Command::ExportCsv(path) => self.exec_export_csv(&path),
+            Command::ExportChrome(path) => self.exec_export_chrome(&path),
+            Command::ExportFolded(path) => self.exec_export_folded(&path),
+            Command::ExportStats(path) => self.exec_export_stats(&path),
+            Command::Screenshot { path, ansi } => {
+                let text = screenshot::buffer_to_text(&self.last_frame, ansi);
+                self.state.message = Some(match std::fs::write(&path, text) {
+                    Ok(()) => format!("wrote screenshot to {}", path),
+                    Err(e) => e.to_string(),
+                });
             }
-
-            let mut detail_state = DetailState {
-                frame_trace: max_trace.clone(),
-            };
-            detail_state
-                .frame_trace
-                .child_traces
-                .sort_by(|a, b| b.total_duration().partial_cmp(&a.total_duration()).unwrap());
-
-            self.state.detail_state = Some(detail_state);
-        } else if let Some(str) = cmd {
-            // must be a number, try to parse it
-            let frame_id: u64 = str.parse().expect("frame_id");
-            self.state.detail_state = Some(DetailState {
-                frame_trace: self.state.trace_data[frame_id as usize].clone(),
-            })
+            Command::ZoomToSelection => {
+                self.state.zoom_y = !self.state.zoom_y;
+                self.state.message = Some(format!(
+                    "y axis fitted to {}",
+                    if self.state.zoom_y {
+                        "frame window"
+                    } else {
+                        "all frames"
+                    }
+                ));
+            }
+            Command::Regressions => self.exec_regressions(),
+            Command::Stats => self.exec_stats(),
+            Command::Gaps => self.exec_gaps(),
+            Command::GapsOff => self.state.gaps.clear(),
+            Command::Reload => self.exec_reload(),
+            Command::AggTarget => self.exec_agg_target(),
+            Command::Top(n) => {
+                self.state.top_frames = n.unwrap_or(DEFAULT_TOP_FRAMES);
+                self.state.top_pick = 0;
+                self.exec_top();
+            }
+            Command::Histogram(bins) => {
+                if let Some(bins) = bins {
+                    self.state.histogram_bins = bins;
+                }
+                self.set_view(View::Histogram);
+            }
+            Command::Sort(order) => match &self.state.detail_state {
+                Some(detail_state) => {
+                    let index = detail_state.index;
+                    self.state.detail_state = Some(DetailState::new(
+                        index,
+                        &self.state.trace_data[index],
+                        order,
+                    ));
+                }
+                None => self.state.message = Some("no frame inspected".to_string()),
+            },
+            Command::Search(text) => self.exec_search(&text),
+            Command::CompareRuns { base, new } => self.exec_compare_runs(base.as_deref(), &new),
+            Command::Merge(None) => self.set_merge(1),
+            Command::Merge(Some(k)) => {
+                self.set_merge(k);
+                self.state.message = Some(format!(
+                    "{} frames, {} source frames each",
+                    self.state.trace_data.len(),
+                    k
+                ));
+            }
+            Command::GotoMostChildren => self.exec_goto_most_children(),
+            Command::FindMessage(text) => self.exec_find_message(&text),
+            Command::ClearHighlight => self.state.highlight = None,
+            Command::FirstOver(threshold) => self.exec_first_over(threshold),
+            Command::Spikes(k) => self.exec_spikes(k),
+            Command::IdleWarn(fraction) => self.exec_idle_warn(fraction),
+            Command::Radix(radix) => self.state.radix = radix,
+            Command::Precision(precision) => self.state.precision = precision,
+            Command::Timeline => self.exec_timeline(),
+            Command::Yank => self.exec_yank(),
+            Command::Level(level) => self.exec_level(level),
+            Command::View(view) if self.lacks_timestamps(view) => {
+                self.state.message = Some("no frame timestamps in trace".to_string());
+            }
+            Command::View(view) => self.set_view(view),
+            Command::DetailMode(mode) => {
+                if mode != DetailMode::List {
+                    self.state.report = None;
+                }
+                if mode == DetailMode::Table {
+                    self.read_all_children();
+                }
+                self.state.detail_mode = mode;
+            }
+            Command::Scale(scale) => self.set_scale(scale),
         }
     }
 
-    fn run(&mut self, mut terminal: Terminal<impl Backend>) -> io::Result<()> {
-        loop {
-            self.draw(&mut terminal)?;
-            if let Event::Key(key) = event::read()? {
-                match self.state.input_mode {
-                    InputMode::Normal => match key.code {
-                        KeyCode::Char(':') => {
-                            self.enter_char(':');
-                            self.state.input_mode = InputMode::Editing;
-                        }
-                        _ => {}
-                    },
-                    InputMode::Editing if key.kind == KeyEventKind::Press => match key.code {
-                        KeyCode::Enter => {
-                            if self.exec_command() {
-                                return Ok(());
-                            }
-                        }
-                        KeyCode::Char(to_insert) => self.enter_char(to_insert),
-                        KeyCode::Backspace => self.delete_char(),
-                        KeyCode::Left => self.move_cursor_left(),
-                        KeyCode::Right => self.move_cursor_right(),
-                        KeyCode::Esc => self.state.input_mode = InputMode::Normal,
-                        _ => {}
-                    },
-                    InputMode::Editing => {}
+    /// Replaces the input with an older (`back`) or newer history entry,
+    /// past the newest with an empty command.
+    fn recall_history(&mut self, back: bool) {
+        let len = self.state.history.len();
+        let index = match (self.state.history_index, back) {
+            (None, true) if len > 0 => Some(len - 1),
+            (None, _) => return,
+            (Some(i), true) => Some(i.saturating_sub(1)),
+            (Some(i), false) if i + 1 < len => Some(i + 1),
+            (Some(_), false) => None,
+        };
+        self.state.history_index = index;
+        self.state.input = match index {
+            Some(i) => self.state.history[i].clone(),
+            None => ":".to_string(),
+        };
+        self.state.character_index = self.state.input.chars().count();
+    }
+
+    /// Completes the last word of the input, repeated Tabs cycle through the
+    /// candidates until another key is pressed.
+    fn complete_input(&mut self) {
+        if !self.state.completions.is_empty() {
+            self.state.completion_index =
+                (self.state.completion_index + 1) % self.state.completions.len();
+            self.state.input = self.state.completions[self.state.completion_index].clone();
+        } else {
+            let mut candidates = completions(&self.state.input);
+            match candidates.len() {
+                0 => return,
+                1 => {
+                    self.state.input = candidates.remove(0) + " ";
+                    self.state.completions.clear();
+                }
+                _ => {
+                    self.state.input = candidates[0].clone();
+                    self.state.completions = candidates;
+                    self.state.completion_index = 0;
                 }
             }
         }
+        self.state.character_index = self.state.input.chars().count();
     }
 
-    fn draw(&mut self, terminal: &mut Terminal<impl Backend>) -> io::Result<()> {
-        terminal.draw(|frame| frame.render_widget(self, frame.area()))?;
-        Ok(())
+    /// Executes the most recent command again, returns true if it was `:q`.
+    fn repeat_last_command(&mut self) -> bool {
+        match self.state.history.last() {
+            Some(last) => {
+                self.state.input = last.clone();
+                self.exec_command()
+            }
+            None => false,
+        }
     }
-}
-
-impl Widget for &mut App {
-    fn render(self, area: Rect, buf: &mut Buffer) {
-        let [frame_bar_area, detail_area, cmd_area] = Layout::vertical([
-            Constraint::Percentage(30),
-            Constraint::Min(10),
-            Constraint::Length(1),
-        ])
-        .areas(area);
-
-        // Create the datasets to fill the chart with
-        let datasets = vec![
-            // Line chart
-            Dataset::default()
-                //.name("frame duration")
-                .marker(symbols::Marker::HalfBlock)
-                .graph_type(GraphType::Bar)
-                .style(Style::default().magenta())
-                .data(&self.state.data),
-        ];
 
-        let mut start = 0.0;
-        let mut end = self.state.data.len() as f64;
-        if let Some(frame_bounds) = &self.state.frame_state {
-            start = frame_bounds.start as f64;
-            end = frame_bounds.end as f64;
+    fn layout(&self) -> layout::Layout {
+        layout::Layout {
+            frame_range: self.state.frame_state.as_ref().map(|f| (f.start, f.end)),
+            scale: self.state.scale,
+            view: self.state.view,
+            compliance_window: self.state.compliance_window,
+            excluded: self.state.excluded.clone(),
+            level: self.state.level.clone(),
+            duration_filter: self.state.duration_filter,
         }
+    }
 
-        // Create the X axis and define its properties
-        let x_axis = Axis::default()
-            .title("frame".red())
-            .style(Style::default().white())
-            .bounds([start, end])
-            .labels([start.to_string(), end.to_string()]);
-
-        let max_str = self.state.max.ceil().to_string();
+    /// Restores a saved layout, returns the parts left out because the
+    /// trace doesn't have them.
+    fn apply_layout(&mut self, layout: layout::Layout) -> Vec<String> {
+        let mut stale = Vec::new();
+        self.state.excluded = layout.excluded;
+        self.state.level = layout.level;
+        self.state.duration_filter = layout.duration_filter;
+        if self.state.level.is_some() {
+            self.read_all_children();
+        }
+        self.set_frames(self.active_frames());
+        self.state.frame_state = match layout.frame_range {
+            Some((start, end))
+                if !self
+                    .state
+                    .millis
+                    .iter()
+                    .any(|(x, _)| (start as f64..=end as f64).contains(x)) =>
+            {
+                stale.push("the frame window".to_string());
+                None
+            }
+            range => range.map(|(start, end)| FrameState { start, end }),
+        };
+        self.state.compliance_window = layout.compliance_window;
+        if self.lacks_timestamps(layout.view) {
+            stale.push(format!("the {:?} view", layout.view).to_lowercase());
+            self.set_view(View::Frames);
+        } else {
+            self.set_view(layout.view);
+        }
+        self.set_scale(layout.scale);
+        stale
+    }
+
+    fn session(&self) -> session::Session {
+        session::Session {
+            layout: self.layout(),
+            inspected: self
+                .state
+                .detail_state
+                .as_ref()
+                .map(|d| self.state.trace_data[d.index].id(d.index)),
+        }
+    }
+
+    /// Restores a saved session, leaving out the frame window and inspected
+    /// frame if they aren't in the loaded frames.
+    fn apply_session(&mut self, session: session::Session) {
+        let mut stale = self.apply_layout(session.layout);
+        if let Some(frame_id) = session.inspected {
+            match self.frame_index(frame_id) {
+                Some(index) => self.inspect_frame(index),
+                None => stale.push(format!("frame {}", fmt_id(frame_id, self.state.radix))),
+            }
+        }
+        self.state.message = Some(if stale.is_empty() {
+            "restored the session".to_string()
+        } else {
+            format!(
+                "restored the session without {}, not in the trace",
+                stale.join(" and ")
+            )
+        });
+    }
+
+    /// Saves the session to `path`, or to the `--session` file without one.
+    fn exec_write(&mut self, path: Option<String>) {
+        let Some(path) = path
+            .map(std::path::PathBuf::from)
+            .or_else(|| self.state.session.clone())
+        else {
+            self.state.message = Some("no session file, use :w <path>".to_string());
+            return;
+        };
+        self.state.message = Some(match session::save(&path, &self.session()) {
+            Ok(()) => format!("saved the session to {}", path.display()),
+            Err(e) => e,
+        });
+    }
+
+    fn exec_export_thumb(&mut self, path: &str, width: u32, height: u32) {
+        let values: Vec<f64> = self.state.data.iter().map(|(_, y)| y.max(0.0)).collect();
+        let max = self.state.scale.apply(self.state.max);
+        self.state.message = Some(
+            match export::write_thumbnail(std::path::Path::new(path), &values, max, width, height) {
+                Ok(()) => format!("wrote {}x{} thumbnail to {}", width, height, path),
+                Err(e) => e,
+            },
+        );
+    }
+
+    /// `(bin center in ms, number of frames)` of the durations of the frames
+    /// in the window, in `bins` equal bins from the shortest to the longest.
+    fn duration_histogram(&self, bins: usize) -> Vec<(f64, f64)> {
+        let (start, end) = self.x_bounds();
+        let millis: Vec<f64> = self
+            .state
+            .millis
+            .iter()
+            .filter(|(x, _)| *x >= start && *x <= end)
+            .map(|(_, ms)| *ms)
+            .collect();
+        let Some(min) = millis.iter().copied().reduce(f64::min) else {
+            return Vec::new();
+        };
+        let max = millis.iter().copied().fold(min, f64::max);
+        let bins = bins.max(1);
+        let width = (max - min) / bins as f64;
+        if width <= 0.0 {
+            return vec![(min, millis.len() as f64)];
+        }
+        let mut counts = vec![0.0; bins];
+        for ms in millis {
+            counts[(((ms - min) / width) as usize).min(bins - 1)] += 1.0;
+        }
+        counts
+            .into_iter()
+            .enumerate()
+            .map(|(i, count)| (min + (i as f64 + 0.5) * width, count))
+            .collect()
+    }
+
+    /// (duration in ms, fraction of frames at most that long) over the frames
+    /// in the frame window, as the corners of a staircase. Equally long
+    /// frames make a single step.
+    fn duration_cdf(&self) -> Vec<(f64, f64)> {
+        let (start, end) = self.x_bounds();
+        let mut millis: Vec<f64> = self
+            .state
+            .millis
+            .iter()
+            .filter(|(x, _)| *x >= start && *x <= end)
+            .map(|(_, ms)| *ms)
+            .collect();
+        millis.sort_by(|a, b| a.partial_cmp(b).unwrap());
+        let n = millis.len() as f64;
+        let mut cdf = Vec::with_capacity(millis.len() * 2);
+        for (i, ms) in millis.into_iter().enumerate() {
+            cdf.push((ms, i as f64 / n));
+            cdf.push((ms, (i + 1) as f64 / n));
+        }
+        cdf
+    }
+
+    /// Child time per target over the frames in the frame window, largest
+    /// first.
+    fn aggregate_by_target(&self) -> Vec<TargetAgg> {
+        let (start, end) = self.x_bounds();
+        let mut frame_total = Duration::ZERO;
+        let mut aggs: Vec<TargetAgg> = Vec::new();
+        for (frame_trace, (x, _)) in self.state.trace_data.iter().zip(&self.state.millis) {
+            if *x < start || *x > end {
+                continue;
+            }
+            frame_total += frame_trace.trace.total_duration();
+            for child in &frame_trace.child_traces {
+                let agg = match aggs.iter_mut().position(|a| a.target == child.target) {
+                    Some(i) => &mut aggs[i],
+                    None => {
+                        aggs.push(TargetAgg {
+                            target: child.target.clone(),
+                            total: Duration::ZERO,
+                            share: 0.0,
+                            calls: 0,
+                        });
+                        aggs.last_mut().unwrap()
+                    }
+                };
+                agg.total += child.total_duration();
+                agg.calls += 1;
+            }
+        }
+        for agg in &mut aggs {
+            agg.share =
+                stats::ratio(agg.total.as_secs_f64(), frame_total.as_secs_f64()).unwrap_or(0.0);
+        }
+        aggs.sort_by_key(|agg| std::cmp::Reverse(agg.total));
+        aggs
+    }
+
+    /// (frame id, ms spent in the children of `target`) of every frame.
+    fn target_series(&self, target: &str) -> Vec<(f64, f64)> {
+        self.state
+            .trace_data
+            .iter()
+            .zip(&self.state.millis)
+            .map(|(frame_trace, (x, _))| {
+                let total: Duration = frame_trace
+                    .child_traces
+                    .iter()
+                    .filter(|child| child.target == target)
+                    .map(|child| child.total_duration())
+                    .sum();
+                (*x, total.as_millis_f64())
+            })
+            .collect()
+    }
+
+    /// Charts the time per frame spent in `target` instead of the frame
+    /// duration, `None` charts the frames again.
+    fn exec_drilldown(&mut self, target: Option<String>) {
+        self.read_all_children();
+        self.state.drilldown = match &target {
+            Some(target) => self.target_series(target),
+            None => Vec::new(),
+        };
+        self.state.message = target
+            .as_ref()
+            .map(|target| format!("charting the time in {}, Esc to go back", target));
+        self.state.drilldown_target = target;
+        self.set_view(View::Frames);
+    }
+
+    /// Whether the :agg target report is shown, so a target can be picked.
+    fn picking_target(&self) -> bool {
+        self.state
+            .report
+            .as_ref()
+            .is_some_and(|report| report.kind == ReportKind::TargetAgg)
+    }
+
+    /// Moves the pick in the :agg target report by `step` rows.
+    fn move_target_pick(&mut self, step: isize) {
+        let targets = self.aggregate_by_target().len();
+        if targets > 0 {
+            self.state.target_pick = self
+                .state
+                .target_pick
+                .saturating_add_signed(step)
+                .min(targets - 1);
+            self.exec_agg_target();
+        }
+    }
+
+    fn drill_into_picked_target(&mut self) {
+        let target = self
+            .aggregate_by_target()
+            .into_iter()
+            .nth(self.state.target_pick)
+            .map(|agg| agg.target);
+        if target.is_some() {
+            self.state.report = None;
+            self.exec_drilldown(target);
+        }
+    }
+
+    fn exec_agg_target(&mut self) {
+        self.read_all_children();
+        let aggs = self.aggregate_by_target();
+        self.state.target_pick = self.state.target_pick.min(aggs.len().saturating_sub(1));
+        let width = aggs
+            .iter()
+            .map(|a| a.target.len())
+            .max()
+            .unwrap_or(0)
+            .max(6);
+        let mut text = format!(
+            "  {:<width$}  {:>12}  {:>8}  {:>8}\n",
+            "target", "total", "% frame", "calls"
+        );
+        for (i, agg) in aggs.iter().enumerate() {
+            text.push_str(&format!(
+                "{} {:<width$}  {:>12}  {:>7.1}%  {:>8}\n",
+                if i == self.state.target_pick {
+                    '>'
+                } else {
+                    ' '
+                },
+                agg.target,
+                fmt_duration(agg.total, self.state.precision),
+                agg.share * 100.0,
+                agg.calls
+            ));
+        }
+        if aggs.is_empty() {
+            text.push_str("No child spans in the frame window\n");
+        }
+        self.state.report = Some(Report {
+            title: "Time per target - Up/Down and Enter to chart one".to_string(),
+            text,
+            kind: ReportKind::TargetAgg,
+        });
+    }
+
+    /// Indices of the `n` longest frames of the frame window, longest first.
+    fn slowest_frames(&self, n: usize) -> Vec<usize> {
+        let (start, end) = self.x_bounds();
+        let mut frames: Vec<usize> = (0..self.state.trace_data.len())
+            .filter(|i| (start..=end).contains(&self.state.millis[*i].0))
+            .collect();
+        // stable, equally long frames stay in order
+        frames.sort_by_key(|i| std::cmp::Reverse(self.state.trace_data[*i].trace.total_duration()));
+        frames.truncate(n);
+        frames
+    }
+
+    /// Whether the :top report is shown, so a frame can be picked.
+    fn picking_frame(&self) -> bool {
+        self.state
+            .report
+            .as_ref()
+            .is_some_and(|report| report.kind == ReportKind::TopFrames)
+    }
+
+    /// Moves the pick in the :top report by `step` rows.
+    fn move_top_pick(&mut self, step: isize) {
+        let frames = self.slowest_frames(self.state.top_frames).len();
+        if frames > 0 {
+            self.state.top_pick = self
+                .state
+                .top_pick
+                .saturating_add_signed(step)
+                .min(frames - 1);
+            self.exec_top();
+        }
+    }
+
+    fn inspect_picked_frame(&mut self) {
+        if let Some(index) = self
+            .slowest_frames(self.state.top_frames)
+            .get(self.state.top_pick)
+        {
+            self.inspect_frame(*index);
+        }
+    }
+
+    fn exec_top(&mut self) {
+        let frames = self.slowest_frames(self.state.top_frames);
+        self.state.top_pick = self.state.top_pick.min(frames.len().saturating_sub(1));
+        let labels: Vec<String> = frames.iter().map(|i| self.frame_label(*i)).collect();
+        let id_width = labels.iter().map(String::len).max().unwrap_or(0).max(2);
+        let target_width = frames
+            .iter()
+            .map(|i| self.state.trace_data[*i].trace.target.len())
+            .max()
+            .unwrap_or(0)
+            .max(6);
+        let mut text = format!(
+            "  {:>id_width$}  {:<target_width$}  {:>12}\n",
+            "id", "target", "duration"
+        );
+        for (row, (index, label)) in frames.iter().zip(&labels).enumerate() {
+            let trace = &self.state.trace_data[*index].trace;
+            text.push_str(&format!(
+                "{} {:>id_width$}  {:<target_width$}  {:>12}\n",
+                if row == self.state.top_pick { '>' } else { ' ' },
+                label,
+                trace.target,
+                fmt_duration(trace.total_duration(), self.state.precision)
+            ));
+        }
+        if frames.is_empty() {
+            text.push_str("No frames in the frame window\n");
+        }
+        self.state.report = Some(Report {
+            title: "Slowest frames - Up/Down and Enter to inspect one".to_string(),
+            text,
+            kind: ReportKind::TopFrames,
+        });
+    }
+
+    fn frame_stats(&self) -> FrameStats {
+        let (start, end) = self.x_bounds();
+        let mut sorted: Vec<f64> = self
+            .state
+            .millis
+            .iter()
+            .filter(|(x, _)| *x >= start && *x <= end)
+            .map(|(_, ms)| *ms)
+            .collect();
+        sorted.sort_by(|a, b| a.partial_cmp(b).unwrap());
+        let (mean, _) = stats::mean_stddev(&sorted).unwrap_or_default();
+        let spikes = stats::spike_threshold(&sorted, DEFAULT_SPIKE_STDDEVS)
+            .map_or(0, |threshold| {
+                sorted.iter().filter(|ms| **ms > threshold).count()
+            });
+        FrameStats {
+            count: sorted.len(),
+            min: sorted.first().copied().unwrap_or(0.0),
+            max: sorted.last().copied().unwrap_or(0.0),
+            mean,
+            p50: stats::percentile(&sorted, 0.5),
+            p95: stats::percentile(&sorted, 0.95),
+            p99: stats::percentile(&sorted, 0.99),
+            spikes,
+        }
+    }
+
+    fn pacing_stats(&self) -> PacingStats {
+        let (start, end) = self.x_bounds();
+        let millis: Vec<f64> = self
+            .state
+            .millis
+            .iter()
+            .filter(|(x, _)| *x >= start && *x <= end)
+            .map(|(_, ms)| *ms)
+            .collect();
+        let deltas: Vec<f64> = millis.windows(2).map(|pair| pair[1] - pair[0]).collect();
+        let Some((_, delta_stddev)) = stats::mean_stddev(&deltas) else {
+            return PacingStats::default();
+        };
+        let threshold = JANK_BUDGET_SHARE * self.state.budget;
+        let janky = deltas
+            .iter()
+            .filter(|delta| delta.abs() > threshold)
+            .count();
+        PacingStats {
+            deltas: deltas.len(),
+            delta_stddev,
+            jank: janky as f64 / deltas.len() as f64,
+        }
+    }
+
+    /// (frame id, mean duration of the last `window` frames) per frame, in
+    /// the chart scale.
+    fn moving_average(&self, window: usize) -> Vec<(f64, f64)> {
+        let millis = &self.state.millis;
+        let window = window.min(millis.len()).max(1);
+        let mut result = Vec::with_capacity(millis.len());
+        let mut sum = 0.0;
+        for (i, (x, ms)) in millis.iter().enumerate() {
+            sum += ms;
+            if i >= window {
+                sum -= millis[i - window].1;
+            }
+            let avg = sum / (i + 1).min(window) as f64;
+            result.push((*x, self.state.scale.apply(avg)));
+        }
+        result
+    }
+
+    /// Values in ms of the grid lines below `max`: 1-2-5 steps of every
+    /// decade on the log10 scale, powers of two on the log2 one and about
+    /// four even steps on the linear one.
+    fn grid_millis(&self, max: f64) -> Vec<f64> {
+        if max <= 0.0 {
+            return Vec::new();
+        }
+        let values: Vec<f64> = match self.state.scale {
+            // below 1ms the log scales are under the x axis
+            Scale::Log10 => (0..=max.log10().floor() as i32)
+                .flat_map(|exponent| [1.0, 2.0, 5.0].map(|m| m * 10f64.powi(exponent)))
+                .skip(1)
+                .collect(),
+            Scale::Log2 => (1..=max.log2().floor() as i32)
+                .map(|k| 2f64.powi(k))
+                .collect(),
+            Scale::Linear | Scale::Dual => {
+                let rough = max / 4.0;
+                let magnitude = 10f64.powi(rough.log10().floor() as i32);
+                let step = [1.0, 2.0, 5.0, 10.0]
+                    .into_iter()
+                    .map(|m| m * magnitude)
+                    .find(|step| *step >= rough)
+                    .unwrap_or(rough);
+                (1..)
+                    .map(|k| k as f64 * step)
+                    .take_while(|v| *v < max)
+                    .collect()
+            }
+        };
+        values.into_iter().filter(|v| *v < max).collect()
+    }
+
+    /// Mean and 1% low frames per second of all frames, as text.
+    fn fps_summary(&self) -> Option<String> {
+        let millis: Vec<f64> = self.state.millis.iter().map(|(_, ms)| *ms).collect();
+        stats::fps(&millis).map(|(mean, low)| format!("{:.1} fps, 1% low {:.1} fps", mean, low))
+    }
+
+    fn exec_fps(&mut self) {
+        self.state.show_fps = !self.state.show_fps;
+        if self.state.show_fps {
+            self.state.message = Some(
+                self.fps_summary()
+                    .unwrap_or("no frames with a duration".to_string()),
+            );
+        }
+    }
+
+    fn exec_stats(&mut self) {
+        if !self.require_frames() {
+            return;
+        }
+        self.state.report = Some(Report {
+            title: "Frame Stats".to_string(),
+            text: self.stats_text(),
+            kind: ReportKind::Text,
+        });
+    }
+
+    /// Ranges of missing frame ids (both included) between the first and the
+    /// last frame read, frames without an id aren't counted. Filtered out
+    /// frames don't make gaps.
+    fn frame_id_gaps(&self) -> Vec<(u64, u64)> {
+        let mut ids: Vec<u64> = self
+            .state
+            .source_frames
+            .iter()
+            .filter_map(|f| f.trace.span.id)
+            .collect();
+        ids.sort_unstable();
+        ids.dedup();
+        ids.windows(2)
+            .filter(|pair| pair[1] - pair[0] > 1)
+            .map(|pair| (pair[0] + 1, pair[1] - 1))
+            .collect()
+    }
+
+    /// Lists the missing frame ids and marks them on the chart.
+    fn exec_gaps(&mut self) {
+        let gaps = self.frame_id_gaps();
+        let missing: u64 = gaps.iter().map(|(first, last)| last - first + 1).sum();
+        let mut text = format!("{} frames missing in {} gaps\n", missing, gaps.len());
+        for (first, last) in &gaps {
+            let (first_label, last_label) = (
+                fmt_id(*first, self.state.radix),
+                fmt_id(*last, self.state.radix),
+            );
+            text.push_str(&match last - first + 1 {
+                1 => format!("{}\n", first_label),
+                n => format!("{}..{}  {} frames\n", first_label, last_label, n),
+            });
+        }
+        self.state.gaps = gaps;
+        self.state.report = Some(Report {
+            title: "Frame id gaps".to_string(),
+            text,
+            kind: ReportKind::Text,
+        });
+    }
+
+    /// The frame stats of the frame window, one per line.
+    fn stats_text(&self) -> String {
+        let frame_stats = self.frame_stats();
+        if frame_stats.count == 0 {
+            "No frames in the frame window\n".to_string()
+        } else {
+            let fmt = |ms: f64| fmt_millis(ms, self.state.precision);
+            format!(
+                "count: {}\nmin:   {}\nmax:   {}\nmean:  {}\np50:   {}\np95:   {}\np99:   {}\nspikes: {}\n",
+                frame_stats.count,
+                fmt(frame_stats.min),
+                fmt(frame_stats.max),
+                fmt(frame_stats.mean),
+                fmt(frame_stats.p50),
+                fmt(frame_stats.p95),
+                fmt(frame_stats.p99),
+                frame_stats.spikes
+            )
+        }
+    }
+
+    /// Copies the frame detail of the inspected frame, or the frame stats
+    /// without one, to the clipboard.
+    fn exec_yank(&mut self) {
+        let text = if self.state.detail_state.is_some() {
+            self.detail_text()
+        } else {
+            self.stats_text()
+        };
+        self.state.message = Some(match clipboard::copy(&text) {
+            Ok(()) => "sent to terminal clipboard (OSC 52)".to_string(),
+            Err(e) => format!("can't copy to the clipboard: {}", e),
+        });
+    }
+
+    /// Writes the frames with ids in the frame window, or all frames, as CSV.
+    fn exec_export_csv(&mut self, path: &str) {
+        let range = self
+            .state
+            .frame_state
+            .as_ref()
+            .map(|f| f.start as u64..=f.end as u64);
+        let frames = self
+            .state
+            .trace_data
+            .iter()
+            .enumerate()
+            .map(|(i, frame_trace)| (frame_trace.id(i), frame_trace))
+            .filter(|(id, _)| range.as_ref().is_none_or(|range| range.contains(id)));
+        self.state.message = Some(
+            match export::write_csv(std::path::Path::new(path), frames) {
+                Ok(rows) => format!("wrote {} frames to {}", rows, path),
+                Err(e) => e,
+            },
+        );
+    }
+
+    /// Indices of the frames in the frame window, with their children read.
+    fn exported_frames(&mut self) -> Vec<usize> {
+        let range = self
+            .state
+            .frame_state
+            .as_ref()
+            .map(|f| f.start as u64..=f.end as u64);
+        let indices: Vec<usize> = (0..self.state.trace_data.len())
+            .filter(|i| {
+                range
+                    .as_ref()
+                    .is_none_or(|range| range.contains(&self.state.trace_data[*i].id(*i)))
+            })
+            .collect();
+        for index in &indices {
+            self.read_children(*index);
+        }
+        indices
+    }
+
+    /// Writes the frames of the frame window and their children in the
+    /// Chrome trace event format.
+    fn exec_export_chrome(&mut self, path: &str) {
+        let indices = self.exported_frames();
+        let frames = indices
+            .into_iter()
+            .map(|i| (self.state.trace_data[i].id(i), &self.state.trace_data[i]));
+        self.state.message = Some(
+            match export::write_chrome_trace(std::path::Path::new(path), frames) {
+                Ok(count) => format!("wrote {} frames to {}", count, path),
+                Err(e) => e,
+            },
+        );
+    }
+
+    /// Writes the span trees of the frames of the frame window as folded
+    /// stacks.
+    fn exec_export_folded(&mut self, path: &str) {
+        let indices = self.exported_frames();
+        let frames = indices.into_iter().map(|i| &self.state.trace_data[i]);
+        self.state.message = Some(
+            match export::write_folded(std::path::Path::new(path), frames) {
+                Ok(lines) => format!("wrote {} stacks to {}", lines, path),
+                Err(e) => e,
+            },
+        );
+    }
+
+    /// Writes the stats of the frame window as JSON.
+    fn exec_export_stats(&mut self, path: &str) {
+        if !self.require_frames() {
+            return;
+        }
+        self.state.message = Some(
+            match export::write_json(std::path::Path::new(path), &self.frame_stats()) {
+                Ok(()) => format!("wrote stats to {}", path),
+                Err(e) => e,
+            },
+        );
+    }
+
+    fn exec_regressions(&mut self) {
+        let millis: Vec<f64> = self.state.millis.iter().map(|(_, ms)| *ms).collect();
+        let change_points = stats::change_points(&millis, REGRESSION_MIN_SEGMENT);
+
+        let mut text = String::new();
+        if change_points.is_empty() {
+            text.push_str("No step changes in frame time found\n");
+        }
+        for cp in change_points {
+            text.push_str(&format!(
+                "frame {}: {} -> {} ({})\n",
+                self.frame_label(cp.index),
+                fmt_millis(cp.before, self.state.precision),
+                fmt_millis(cp.after, self.state.precision),
+                match stats::ratio(cp.after - cp.before, cp.before) {
+                    Some(change) => format!("{:+.1}%", change * 100.0),
+                    None => "-".to_string(),
+                }
+            ));
+        }
+        self.state.report = Some(Report {
+            title: "Regressions".to_string(),
+            text,
+            kind: ReportKind::Text,
+        });
+    }
+
+    fn exec_compare_runs(&mut self, base: Option<&str>, new: &str) {
+        if base.is_none() {
+            self.read_all_children();
+        }
+        let read = |path: &str| {
+            trace::read_trace_file(
+                std::path::Path::new(path),
+                &self.state.frame_span,
+                FrameWindow::default(),
+                self.state.threads,
+            )
+            .map(|(frames, _)| frames)
+        };
+        let frames = match base {
+            Some(base) => read(base).and_then(|base| Ok((base, read(new)?))),
+            None => read(new).map(|new| (self.state.source_frames.clone(), new)),
+        };
+        match frames {
+            Ok((base_frames, new_frames)) => {
+                self.state.report = Some(Report {
+                    title: format!("{} vs. {}", base.unwrap_or("loaded frames"), new),
+                    text: compare::compare_runs(
+                        &base_frames,
+                        &new_frames,
+                        self.state.budget,
+                        self.state.precision,
+                    ),
+                    kind: ReportKind::Text,
+                });
+            }
+            Err(e) => self.state.message = Some(e.to_string()),
+        }
+    }
+
+    fn set_note(&mut self, frame_id: u64, note: &str) {
+        if note.is_empty() {
+            self.state.notes.remove(&frame_id);
+        } else {
+            self.state.notes.insert(frame_id, note.to_string());
+        }
+    }
+
+    /// The ids the notes of the frame at `index` are kept under, those of the
+    /// frames in it if it is merged so notes outlast :merge.
+    fn note_ids(&self, index: usize) -> Vec<u64> {
+        let frame_trace = &self.state.trace_data[index];
+        if frame_trace.merged_ids.is_empty() {
+            vec![frame_trace.id(index)]
+        } else {
+            frame_trace.merged_ids.clone()
+        }
+    }
+
+    fn inspected_note_id(&self) -> Option<u64> {
+        self.state
+            .detail_state
+            .as_ref()
+            .map(|d| self.note_ids(d.index)[0])
+    }
+
+    /// Opens the input prefilled with the note of the inspected frame.
+    fn start_note_edit(&mut self) {
+        if let Some(frame_id) = self.inspected_note_id() {
+            self.state.input = self.state.notes.get(&frame_id).cloned().unwrap_or_default();
+            self.state.character_index = self.state.input.chars().count();
+            self.state.input_mode = InputMode::Note;
+        }
+    }
+
+    fn save_note(&mut self) {
+        if let Some(frame_id) = self.inspected_note_id() {
+            let note = self.state.input.trim().to_string();
+            self.set_note(frame_id, &note);
+        }
+        self.state.input.clear();
+        self.state.character_index = 0;
+        self.state.input_mode = InputMode::Normal;
+    }
+
+    /// Shows the frame at `index` in the detail panel, children in the order
+    /// chosen for the previously inspected frame (descending duration at
+    /// first).
+    fn inspect_frame(&mut self, index: usize) {
+        self.read_children(index);
+        let order = self
+            .state
+            .detail_state
+            .as_ref()
+            .map_or(ChildOrder::default(), |d| d.order);
+        let detail_state = DetailState::new(index, &self.state.trace_data[index], order);
+        self.state.selected_frame = None;
+        self.state.detail_scroll = 0;
+
+        self.state.report = None;
+        if self.state.detail_mode == DetailMode::Table {
+            self.state.detail_mode = DetailMode::List;
+        }
+        self.state.detail_state = Some(detail_state);
+    }
+
+    /// Highlights and lists frames spending more than `fraction` of their
+    /// time idle.
+    fn exec_idle_warn(&mut self, fraction: f64) {
+        let mut frames = Vec::new();
+        let mut text = String::new();
+        for (i, frame_trace) in self.state.trace_data.iter().enumerate() {
+            let idle = frame_trace.trace.idle().as_millis_f64();
+            let total = frame_trace.trace.total_duration().as_millis_f64();
+            if let Some(ratio) = stats::ratio(idle, total)
+                && ratio > fraction
+            {
+                frames.push(i);
+                text.push_str(&format!(
+                    "frame {}: {:.0}% idle ({} of {})\n",
+                    self.frame_label(i),
+                    ratio * 100.0,
+                    fmt_millis(idle, self.state.precision),
+                    fmt_millis(total, self.state.precision)
+                ));
+            }
+        }
+        if frames.is_empty() {
+            text.push_str(&format!("No frames above {:.0}% idle\n", fraction * 100.0));
+        }
+
+        self.state.report = Some(Report {
+            title: format!("Idle > {:.0}% ({} frames)", fraction * 100.0, frames.len()),
+            text,
+            kind: ReportKind::Text,
+        });
+        self.state.highlight = Some(Highlight {
+            label: format!("idle > {:.0}%", fraction * 100.0),
+            color: self.state.theme.idle,
+            frames,
+        });
+    }
+
+    /// Displayed id of the frame at `index`.
+    fn frame_label(&self, index: usize) -> String {
+        fmt_id(self.state.millis[index].0 as u64, self.state.radix)
+    }
+
+    /// Moves the frame window so the frame at `index` is in its center,
+    /// keeping the current window width.
+    fn center_on(&mut self, index: usize) {
+        let width = match &self.state.frame_state {
+            Some(frame_state) => frame_state.end.saturating_sub(frame_state.start),
+            None => DEFAULT_WINDOW.min(self.state.data.len()),
+        };
+        let x = self.state.millis[index].0 as usize;
+        let start = x.saturating_sub(width / 2);
+        self.state.frame_state = Some(FrameState {
+            start,
+            end: start + width,
+        });
+    }
+
+    /// The frame window, all frames if none is set.
+    fn window(&self) -> (usize, usize) {
+        match &self.state.frame_state {
+            Some(frame_state) => (frame_state.start, frame_state.end.max(frame_state.start)),
+            None => (0, self.state.data.len()),
+        }
+    }
+
+    /// Scales the width of the frame window by `factor`, keeping its center.
+    fn zoom(&mut self, factor: f64) {
+        let len = self.state.data.len();
+        let (start, end) = self.window();
+        let center = start + (end - start) / 2;
+        let width = (((end - start) as f64 * factor).round() as usize).clamp(1, len.max(1));
+        let start = center
+            .saturating_sub(width / 2)
+            .min(len.saturating_sub(width));
+        self.state.frame_state = Some(FrameState {
+            start,
+            end: start + width,
+        });
+    }
+
+    /// Moves the frame window by a quarter of its width, left for a negative
+    /// `direction`.
+    fn pan(&mut self, direction: isize) {
+        let len = self.state.data.len();
+        let (start, end) = self.window();
+        let width = (end - start).min(len);
+        let step = (width / 4).max(1);
+        let start = if direction < 0 {
+            start.saturating_sub(step)
+        } else {
+            (start + step).min(len.saturating_sub(width))
+        };
+        self.state.frame_state = Some(FrameState {
+            start,
+            end: start + width,
+        });
+    }
+
+    /// Selects and inspects the frame at `index` and centers the frame
+    /// window on it.
+    fn jump_to_frame(&mut self, index: usize) {
+        if index >= self.state.trace_data.len() {
+            return;
+        }
+        self.inspect_frame(index);
+        self.center_on(index);
+        self.state.selected_frame = Some(index);
+    }
+
+    /// Moves the selection `delta` frames, staying within the frame window,
+    /// and inspects the selected frame. Without a selection it starts at the
+    /// inspected frame.
+    fn select_frame(&mut self, delta: isize) {
+        let (start, end) = self.x_bounds();
+        let visible = |(x, _): &(f64, f64)| *x >= start && *x <= end;
+        let millis = &self.state.millis;
+        let (Some(first), Some(last)) = (
+            millis.iter().position(visible),
+            millis.iter().rposition(visible),
+        ) else {
+            return;
+        };
+        let current =
+            self.state
+                .selected_frame
+                .or(self.state.detail_state.as_ref().map(|d| d.index));
+        let index = match current {
+            Some(current) => current.saturating_add_signed(delta).clamp(first, last),
+            None if delta < 0 => last,
+            None => first,
+        };
+        self.inspect_frame(index);
+        self.state.selected_frame = Some(index);
+    }
+
+    fn exec_first_over(&mut self, threshold: Duration) {
+        let index = self
+            .state
+            .trace_data
+            .iter()
+            .position(|f| f.trace.total_duration() > threshold);
+        match index {
+            Some(index) => {
+                self.inspect_frame(index);
+                self.center_on(index);
+            }
+            None => {
+                self.state.message = Some(format!(
+                    "no frame over {}",
+                    fmt_duration(threshold, self.state.precision)
+                ))
+            }
+        }
+    }
+
+    /// Ids of the frames longer than the mean duration plus `k` standard
+    /// deviations, none if all frames take the same time.
+    fn spike_frames(&self, k: f64) -> Vec<u64> {
+        let millis: Vec<f64> = self.state.millis.iter().map(|(_, ms)| *ms).collect();
+        let Some(threshold) = stats::spike_threshold(&millis, k) else {
+            return Vec::new();
+        };
+        (0..millis.len())
+            .filter(|i| millis[*i] > threshold)
+            .map(|i| self.state.trace_data[i].id(i))
+            .collect()
+    }
+
+    /// Highlights and lists the frames more than `k` standard deviations
+    /// above the mean duration.
+    fn exec_spikes(&mut self, k: f64) {
+        let ids: HashSet<u64> = self.spike_frames(k).into_iter().collect();
+        let frames: Vec<usize> = (0..self.state.trace_data.len())
+            .filter(|i| ids.contains(&self.state.trace_data[*i].id(*i)))
+            .collect();
+        let mut text = String::new();
+        for i in &frames {
+            text.push_str(&format!(
+                "frame {}: {}\n",
+                self.frame_label(*i),
+                fmt_millis(self.state.millis[*i].1, self.state.precision)
+            ));
+        }
+        if frames.is_empty() {
+            text.push_str(&format!("No frames above mean + {}σ\n", k));
+        }
+        self.state.report = Some(Report {
+            title: format!("Spikes > mean + {}σ ({} frames)", k, frames.len()),
+            text,
+            kind: ReportKind::Text,
+        });
+        self.state.highlight = Some(Highlight {
+            label: format!("spikes > {}σ", k),
+            color: self.state.theme.spike,
+            frames,
+        });
+    }
+
+    /// Highlights frames with a child whose message contains `text`, ignoring case.
+    fn exec_find_message(&mut self, text: &str) {
+        self.read_all_children();
+        let needle = text.to_lowercase();
+        let frames: Vec<usize> = self
+            .state
+            .trace_data
+            .iter()
+            .enumerate()
+            .filter(|(_, f)| {
+                f.child_traces
+                    .iter()
+                    .any(|child| child.fields.message.to_lowercase().contains(&needle))
+            })
+            .map(|(i, _)| i)
+            .collect();
+        self.state.message = Some(format!("{} frames with message '{}'", frames.len(), text));
+        self.state.highlight = Some(Highlight {
+            label: format!("message '{}'", text),
+            color: self.state.theme.search,
+            frames,
+        });
+    }
+
+    fn exec_search(&mut self, text: &str) {
+        let needle = text.to_lowercase();
+        let matches: Vec<usize> = self
+            .state
+            .trace_data
+            .iter()
+            .enumerate()
+            .filter(|(_, f)| f.trace.target.to_lowercase().contains(&needle))
+            .map(|(i, _)| i)
+            .collect();
+        self.state.message = Some(format!(
+            "{} frames with target '{}', n/N to step through",
+            matches.len(),
+            text
+        ));
+        self.state.search = Some(Search {
+            matches,
+            current: None,
+        });
+    }
+
+    /// Inspects the next search match, the previous one for a negative
+    /// `direction`.
+    fn step_search(&mut self, direction: isize) {
+        let Some(search) = &mut self.state.search else {
+            return;
+        };
+        let total = search.matches.len();
+        if total == 0 {
+            return;
+        }
+        let current = match search.current {
+            Some(current) if direction < 0 => (current + total - 1) % total,
+            Some(current) => (current + 1) % total,
+            None if direction < 0 => total - 1,
+            None => 0,
+        };
+        search.current = Some(current);
+        let index = search.matches[current];
+        self.inspect_frame(index);
+        self.center_on(index);
+        self.state.message = Some(format!("match {}/{}", current + 1, total));
+    }
+
+    fn exec_goto_most_children(&mut self) {
+        self.read_all_children();
+        let index = self
+            .state
+            .trace_data
+            .iter()
+            .enumerate()
+            .rev()
+            .max_by_key(|(_, f)| f.child_traces.len())
+            .map(|(i, _)| i);
+        if let Some(index) = index {
+            self.inspect_frame(index);
+            self.state.message = Some(format!(
+                "frame {} has {} children",
+                self.frame_label(index),
+                self.state.trace_data[index].child_traces.len()
+            ));
+        }
+    }
+
+    /// Shows "No frames found" and returns false if there are no frames to
+    /// run a command on.
+    fn require_frames(&mut self) -> bool {
+        if self.state.trace_data.is_empty() {
+            self.state.message = Some("No frames found".to_string());
+            return false;
+        }
+        true
+    }
+
+    /// `:f inspect <max|min|id>`
+    fn exec_frame_inspect(&mut self, target: InspectTarget) {
+        if !self.require_frames() {
+            return;
+        }
+        self.state.report = None;
+        let duration = |(_, f): &(usize, &FrameTrace)| f.trace.total_duration();
+        let frames = self.state.trace_data.iter().enumerate();
+        let index = match target {
+            // the first of equally long frames
+            InspectTarget::Max => frames.rev().max_by_key(duration).map(|(i, _)| i),
+            InspectTarget::Min => frames.min_by_key(duration).map(|(i, _)| i),
+            InspectTarget::Id(frame_id) => self.frame_index(frame_id),
+        };
+        if let Some(index) = index {
+            self.inspect_frame(index);
+        }
+    }
+
+    /// Index of the frame with the id `frame_id`. Shows an error and returns
+    /// `None` if there is no such frame.
+    fn frame_index(&mut self, frame_id: u64) -> Option<usize> {
+        let index =
+            (0..self.state.trace_data.len()).find(|i| self.state.trace_data[*i].id(*i) == frame_id);
+        if index.is_none() {
+            self.state.message = Some(format!("no frame with id {}", frame_id));
+        }
+        index
+    }
+
+    /// Inspects the frame with the given id and centers the frame window on it.
+    fn exec_frame_goto(&mut self, id: u64) {
+        if let Some(index) = self.frame_index(id) {
+            self.inspect_frame(index);
+            self.center_on(index);
+        }
+    }
+
+    /// Reads the children of the frame at `index` if they haven't been yet,
+    /// with --mmap.
+    fn read_children(&mut self, index: usize) {
+        if let Err(e) = self.state.trace_data[index].read_children() {
+            self.state.message = Some(e.to_string());
+        }
+    }
+
+    /// Reads the children of every frame that haven't been yet, for the
+    /// commands looking at the children of all frames. They are read into
+    /// the source frames, so merging and filtering again keeps them.
+    fn read_all_children(&mut self) {
+        if self
+            .state
+            .source_frames
+            .iter()
+            .all(|f| f.unread_children.is_empty())
+        {
+            return;
+        }
+        for frame_trace in &mut self.state.source_frames {
+            if let Err(e) = frame_trace.read_children() {
+                self.state.message = Some(e.to_string());
+                break;
+            }
+        }
+        let frames = self.active_frames();
+        if self.state.level.is_some() {
+            // frames may match the level by their children now
+            self.set_frames(frames);
+        } else {
+            self.state.trace_data = frames;
+        }
+    }
 
-        // Create the Y axis and define its properties
+    /// Compares the children of two frames, matched by target and span name,
+    /// sorted by the largest change first.
+    fn exec_frame_diff(&mut self, first: u64, second: u64) {
+        let (Some(first), Some(second)) = (self.frame_index(first), self.frame_index(second))
+        else {
+            return;
+        };
+        self.read_children(first);
+        self.read_children(second);
+
+        // (target, name) -> duration in the first and second frame
+        let mut rows: HashMap<(&str, &str), [Option<Duration>; 2]> = HashMap::new();
+        for (column, index) in [first, second].into_iter().enumerate() {
+            for child in &self.state.trace_data[index].child_traces {
+                let entry = rows
+                    .entry((child.target.as_str(), child.span.name.as_str()))
+                    .or_default();
+                entry[column] = Some(entry[column].unwrap_or_default() + child.total_duration());
+            }
+        }
+        let delta = |durations: &[Option<Duration>; 2]| {
+            let [a, b] = durations.map(|d| d.unwrap_or_default().as_secs_f64());
+            b - a
+        };
+        let mut rows: Vec<_> = rows.into_iter().collect();
+        rows.sort_by(|(_, a), (_, b)| delta(b).abs().total_cmp(&delta(a).abs()));
+
+        let precision = self.state.precision;
+        let cell =
+            |d: Option<Duration>| d.map_or("—".to_string(), |d| fmt_duration(d, precision));
+        let width = rows
+            .iter()
+            .map(|((target, name), _)| target.len() + name.len() + 1)
+            .max()
+            .unwrap_or(0)
+            .max(5);
+        let first_id = self.state.trace_data[first].id(first);
+        let second_id = self.state.trace_data[second].id(second);
+        let mut text = format!(
+            "{:<width$}  {:>12}  {:>12}  {:>12}\n",
+            "child",
+            format!("#{}", first_id),
+            format!("#{}", second_id),
+            "delta"
+        );
+        for ((target, name), durations) in &rows {
+            let delta = delta(durations);
+            let sign = if delta < 0.0 { "-" } else { "+" };
+            text.push_str(&format!(
+                "{:<width$}  {:>12}  {:>12}  {:>12}\n",
+                format!("{}/{}", target, name),
+                cell(durations[0]),
+                cell(durations[1]),
+                format!(
+                    "{}{}",
+                    sign,
+                    fmt_duration(Duration::from_secs_f64(delta.abs()), precision)
+                )
+            ));
+        }
+        if rows.is_empty() {
+            text.push_str("No child spans in either frame\n");
+        }
+        self.state.report = Some(Report {
+            title: format!("Frame {} vs {}", first_id, second_id),
+            text,
+            kind: ReportKind::Text,
+        });
+    }
+
+    fn run(&mut self, mut terminal: Terminal<impl Backend>) -> io::Result<()> {
+        loop {
+            if std::mem::take(&mut self.state.dirty) {
+                self.draw(&mut terminal)?;
+            }
+            // only block on input if there is nothing else to wait for
+            if !self.follow || event::poll(self.poll_interval)? {
+                let event = event::read()?;
+                // resizes and ignored keys are redrawn too
+                self.state.dirty = true;
+                match event {
+                    Event::Key(key) if self.handle_key(key) => return Ok(()),
+                    Event::Mouse(mouse) => self.handle_mouse(mouse),
+                    _ => {}
+                }
+            }
+            self.poll_follower();
+        }
+    }
+
+    fn poll_follower(&mut self) {
+        if self.follow && self.follower.is_none() {
+            // the file couldn't be opened so far, try again until it can
+            if let Ok((frames, skipped)) = self.open_follower() {
+                self.state.source_frames = frames;
+                self.set_frames(self.active_frames());
+                self.publish_metrics();
+                self.state.load_error = None;
+                self.state.message = skipped_message(&skipped);
+                self.state.dirty = true;
+            }
+        } else if let Some(follower) = &mut self.follower {
+            let polled = follower.poll();
+            if !polled.frames.is_empty() {
+                self.append_frames(polled.frames);
+                self.state.dirty = true;
+            }
+            let message = polled
+                .error
+                .map(|e| e.to_string())
+                .or_else(|| skipped_message(&polled.skipped));
+            if message.is_some() {
+                self.state.dirty |= self.state.message != message;
+                self.state.message = message;
+            }
+        }
+    }
+
+    /// Scrolls the detail panel by `lines`, up for negative ones. Scrolling
+    /// past the end is undone when drawing.
+    fn scroll_detail(&mut self, lines: i16) {
+        self.state.detail_scroll = self.state.detail_scroll.saturating_add_signed(lines);
+    }
+
+    /// Index of the frame charted at the column `x` of the chart, if the
+    /// position is over the bars.
+    fn frame_at(&self, x: u16, y: u16) -> Option<usize> {
+        let graph = self.state.chart_graph?;
+        if self.state.trace_data.is_empty() || !graph.contains(Position { x, y }) {
+            return None;
+        }
+        // the inverse of the chart placing a point at its share of the width
+        let (start, end) = self.chart_x_bounds();
+        let share = f64::from(x - graph.x) / f64::from(graph.width.saturating_sub(1).max(1));
+        let chart_x = start + share * (end - start);
+        (0..self.state.trace_data.len())
+            .min_by(|a, b| {
+                let distance = |i: &usize| (self.chart_x(*i) - chart_x).abs();
+                distance(a).total_cmp(&distance(b))
+            })
+            .filter(|i| (start..=end).contains(&self.chart_x(*i)))
+    }
+
+    /// Shows a tooltip for the frame under the mouse, a click inspects it.
+    fn handle_mouse(&mut self, mouse: MouseEvent) {
+        match mouse.kind {
+            MouseEventKind::Moved => {
+                self.state.hover = Some((mouse.column, mouse.row));
+            }
+            MouseEventKind::Down(MouseButton::Left) => {
+                self.state.hover = Some((mouse.column, mouse.row));
+                if let Some(index) = self.frame_at(mouse.column, mouse.row) {
+                    self.inspect_frame(index);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// Small box next to the mouse with the id and duration of the frame
+    /// under it.
+    fn render_tooltip(&self, area: Rect, buf: &mut Buffer) {
+        let Some((x, y)) = self.state.hover else {
+            return;
+        };
+        let Some(index) = self.frame_at(x, y) else {
+            return;
+        };
+        let text = format!(
+            "frame {}  {}",
+            self.frame_label(index),
+            fmt_millis(
+                self.state.trace_data[index]
+                    .trace
+                    .total_duration()
+                    .as_millis_f64(),
+                self.state.precision
+            )
+        );
+        let width = text.chars().count() as u16 + 2;
+        let tooltip = area.clamp(Rect {
+            x: (x + 1).min(area.right().saturating_sub(width)),
+            y: y.saturating_sub(3).max(area.y),
+            width,
+            height: 3,
+        });
+        Clear.render(tooltip, buf);
+        Paragraph::new(text)
+            .block(Block::bordered())
+            .render(tooltip, buf);
+    }
+
+    /// Handles a key press, returns true if the app should quit.
+    fn handle_key(&mut self, key: KeyEvent) -> bool {
+        if self.state.show_help {
+            if key.kind == KeyEventKind::Press {
+                self.state.show_help = false;
+            }
+            return false;
+        }
+        let pending_g = std::mem::take(&mut self.state.pending_g);
+        match self.state.input_mode {
+            InputMode::Normal => match key.code {
+                KeyCode::Char(':') => {
+                    self.enter_char(':');
+                    self.state.input_mode = InputMode::Editing;
+                }
+                KeyCode::Char('m') => self.start_note_edit(),
+                KeyCode::Char('?') => self.state.show_help = true,
+                KeyCode::Char('y') => self.exec_yank(),
+                KeyCode::PageDown => self.scroll_detail(DETAIL_SCROLL_STEP),
+                KeyCode::PageUp => self.scroll_detail(-DETAIL_SCROLL_STEP),
+                KeyCode::Char('d') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                    self.scroll_detail(DETAIL_SCROLL_STEP)
+                }
+                KeyCode::Char('u') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                    self.scroll_detail(-DETAIL_SCROLL_STEP)
+                }
+                KeyCode::Char('.') => {
+                    if self.repeat_last_command() {
+                        return true;
+                    }
+                }
+                KeyCode::Char('+') | KeyCode::Char('=') => self.zoom(0.5),
+                KeyCode::Char('-') => self.zoom(2.0),
+                KeyCode::Char('h') | KeyCode::Left => self.pan(-1),
+                KeyCode::Char('l') | KeyCode::Right => self.pan(1),
+                KeyCode::Char('L') => self.set_scale(match self.state.scale {
+                    Scale::Linear => Scale::Log10,
+                    _ => Scale::Linear,
+                }),
+                KeyCode::Char('g') if pending_g => self.jump_to_frame(0),
+                KeyCode::Char('g') => self.state.pending_g = true,
+                KeyCode::Char('G') => {
+                    if let Some(last) = self.state.trace_data.len().checked_sub(1) {
+                        self.jump_to_frame(last)
+                    }
+                }
+                KeyCode::Char('j') => self.select_frame(1),
+                KeyCode::Char('k') => self.select_frame(-1),
+                KeyCode::Char('n') => self.step_search(1),
+                KeyCode::Char('N') => self.step_search(-1),
+                KeyCode::Up if self.picking_target() => self.move_target_pick(-1),
+                KeyCode::Down if self.picking_target() => self.move_target_pick(1),
+                KeyCode::Enter if self.picking_target() => self.drill_into_picked_target(),
+                KeyCode::Up if self.picking_frame() => self.move_top_pick(-1),
+                KeyCode::Down if self.picking_frame() => self.move_top_pick(1),
+                KeyCode::Enter if self.picking_frame() => self.inspect_picked_frame(),
+                KeyCode::Esc if self.state.search.is_some() => {
+                    self.state.search = None;
+                    self.state.message = None;
+                }
+                KeyCode::Esc if self.state.drilldown_target.is_some() => self.exec_drilldown(None),
+                _ => {}
+            },
+            InputMode::Editing if key.kind == KeyEventKind::Press => {
+                if key.code != KeyCode::Tab {
+                    self.state.completions.clear();
+                }
+                match key.code {
+                    KeyCode::Enter => {
+                        if self.exec_command() {
+                            return true;
+                        }
+                    }
+                    KeyCode::Char(to_insert) => self.enter_char(to_insert),
+                    KeyCode::Backspace => self.delete_char(),
+                    KeyCode::Left => self.move_cursor_left(),
+                    KeyCode::Right => self.move_cursor_right(),
+                    KeyCode::Up => self.recall_history(true),
+                    KeyCode::Down => self.recall_history(false),
+                    KeyCode::Tab => self.complete_input(),
+                    KeyCode::Esc => {
+                        self.state.history_index = None;
+                        self.state.input_mode = InputMode::Normal;
+                    }
+                    _ => {}
+                }
+            }
+            InputMode::Note if key.kind == KeyEventKind::Press => match key.code {
+                KeyCode::Enter => self.save_note(),
+                KeyCode::Char(to_insert) => self.enter_char(to_insert),
+                KeyCode::Backspace => self.delete_char(),
+                KeyCode::Left => self.move_cursor_left(),
+                KeyCode::Right => self.move_cursor_right(),
+                KeyCode::Esc => {
+                    self.state.input.clear();
+                    self.state.character_index = 0;
+                    self.state.input_mode = InputMode::Normal;
+                }
+                _ => {}
+            },
+            InputMode::Editing | InputMode::Note => {}
+        }
+        false
+    }
+
+    fn draw(&mut self, terminal: &mut Terminal<impl Backend>) -> io::Result<()> {
+        let frame = terminal.draw(|frame| frame.render_widget(&mut *self, frame.area()))?;
+        self.last_frame = frame.buffer.clone();
+        Ok(())
+    }
+}
+
+/// Where a chart rendered to `area` draws its points: right of the y axis,
+/// above the x axis and below the title line. Found by the corner of the
+/// axes, the chart doesn't tell.
+fn graph_area(area: Rect, buf: &Buffer) -> Option<Rect> {
+    let corner = area
+        .rows()
+        .rev()
+        .flat_map(|row| row.columns())
+        .find(|cell| buf[(cell.x, cell.y)].symbol() == symbols::line::BOTTOM_LEFT)?;
+    let top = area.y + 1;
+    Some(Rect {
+        x: corner.x + 1,
+        y: top,
+        width: area.right().saturating_sub(corner.x + 1),
+        height: corner.y.saturating_sub(top),
+    })
+}
+
+/// width of the right hand axis of the dual scale
+const LOG_AXIS_WIDTH: u16 = 8;
+
+/// Row of `graph` at `fraction` (0..=1) of its height from the bottom.
+fn graph_row(graph: Rect, fraction: f64) -> u16 {
+    let bottom = graph.bottom() - 1;
+    bottom - (fraction * (graph.height - 1) as f64).round() as u16
+}
+
+impl App {
+    /// Right hand axis of the dual scale: logarithmic 1-2-5 ticks, placed at
+    /// their height on the linear chart plotting to `graph`.
+    fn render_log_axis(&self, area: Rect, graph: Rect, buf: &mut Buffer) {
+        let max = self.y_axis_max();
+        if graph.height < 2 || max <= 0.0 {
+            return;
+        }
+        let bottom = graph.bottom() - 1;
+        for y in graph.y..=bottom {
+            buf[(area.x, y)].set_symbol("│");
+        }
+
+        let mut last_row = None;
+        let mut exponent = max.log10().floor() as i32;
+        'ticks: loop {
+            for mantissa in [5.0, 2.0, 1.0] {
+                let value = mantissa * 10f64.powi(exponent);
+                if value > max {
+                    continue;
+                }
+                let row = graph_row(graph, value / max);
+                if row == bottom {
+                    break 'ticks;
+                }
+                if last_row != Some(row) {
+                    buf.set_string(
+                        area.x + 1,
+                        row,
+                        fmt_millis(value, 0),
+                        Style::default().fg(self.state.theme.axis),
+                    );
+                    last_row = Some(row);
+                }
+            }
+            exponent -= 1;
+        }
+    }
+
+    /// Labels of the grid values between the bottom and top of a log scale
+    /// y axis, at their height on the chart plotting to `graph`. The axis
+    /// only spaces its labels evenly.
+    fn render_log_labels(&self, x: u16, graph: Rect, buf: &mut Buffer) {
+        let max = self.y_axis_max();
+        let y_max = self.state.scale.apply(max);
+        if graph.height < 2 || y_max <= 0.0 {
+            return;
+        }
+        // left of the y axis line
+        let width = graph.x.saturating_sub(x + 1) as usize;
+        let mut last_row = None;
+        for ms in self.grid_millis(max) {
+            let row = graph_row(graph, self.state.scale.apply(ms) / y_max);
+            // the bottom and top rows have the labels of the axis
+            if row == graph.y || row == graph.bottom() - 1 || last_row == Some(row) {
+                continue;
+            }
+            buf.set_stringn(
+                x,
+                row,
+                fmt_millis(ms, 0),
+                width,
+                Style::default().fg(self.state.theme.axis),
+            );
+            last_row = Some(row);
+        }
+    }
+
+    /// Share of each child target in the frame's time: one stacked bar over
+    /// the full width, then a bar per target.
+    fn render_target_breakdown(&self, index: usize, area: Rect, buf: &mut Buffer) {
+        let frame_trace = &self.state.trace_data[index];
+        let block = Block::bordered()
+            .title_style(self.title_style())
+            .title(format!("Targets of frame {}", self.frame_label(index)));
+        let inner = block.inner(area);
+        block.render(area, buf);
+
+        let totals = frame_trace.time_by_target();
+        let frame_millis = frame_trace.trace.total_duration().as_millis_f64();
+        let color = |i: usize| self.state.theme.palette_color(i);
+        let share = |d: Duration| stats::ratio(d.as_millis_f64(), frame_millis).unwrap_or(0.0);
+
+        let width = inner.width as usize;
+        let mut stacked = Vec::new();
+        let mut used = 0;
+        for (i, (_, total)) in totals.iter().enumerate() {
+            let cells = ((share(*total) * width as f64).round() as usize).min(width - used);
+            stacked.push(Span::styled(
+                "█".repeat(cells),
+                Style::default().fg(color(i)),
+            ));
+            used += cells;
+        }
+        stacked.push(Span::raw("░".repeat(width - used)));
+
+        let bar_width = (width / 3).max(10);
+        let mut lines = vec![Line::from(stacked), Line::raw("")];
+        for (i, (target, total)) in totals.iter().enumerate() {
+            let cells = ((share(*total) * bar_width as f64).round() as usize).min(bar_width);
+            lines.push(Line::from(vec![
+                Span::styled("█".repeat(cells), Style::default().fg(color(i))),
+                Span::raw("░".repeat(bar_width - cells)),
+                Span::raw(format!(
+                    " {:5.1}% {} - {}",
+                    share(*total) * 100.0,
+                    target,
+                    fmt_duration(*total, self.state.precision)
+                )),
+            ]));
+        }
+        Paragraph::new(lines).render(inner, buf);
+    }
+
+    /// Gantt chart of the children of a frame, one row each. Children are
+    /// placed by their timestamps if the trace has them, else one after the
+    /// other in the order they closed.
+    fn render_timeline(&self, index: usize, area: Rect, buf: &mut Buffer) {
+        let frame_trace = &self.state.trace_data[index];
+        let frame_duration = frame_trace.trace.total_duration();
+        let block = Block::bordered()
+            .title_style(self.title_style())
+            .title(format!(
+                "Timeline of frame {} - {}",
+                self.frame_label(index),
+                fmt_duration(frame_duration, self.state.precision)
+            ));
+        let inner = block.inner(area);
+        block.render(area, buf);
+
+        let frame_millis = frame_duration.as_millis_f64();
+        let frame_start = frame_trace
+            .trace
+            .timestamp
+            .map(|ts| ts - chrono::Duration::from_std(frame_duration).unwrap_or_default());
+        let mut children: Vec<(f64, &trace::Trace)> =
+            Vec::with_capacity(frame_trace.child_traces.len());
+        let mut offset = 0.0;
+        for child in &frame_trace.child_traces {
+            let millis = child.total_duration().as_millis_f64();
+            let start = match (frame_start, child.timestamp) {
+                (Some(frame_start), Some(ts)) => {
+                    let delta = ts - frame_start;
+                    delta.num_microseconds().unwrap_or(0) as f64 / 1000.0 - millis
+                }
+                _ => offset,
+            };
+            offset += millis;
+            children.push((start.max(0.0), child));
+        }
+        children.sort_by(|a, b| a.0.partial_cmp(&b.0).unwrap());
+
+        let label_width = (inner.width / 3).min(24) as usize;
+        let bar_width = (inner.width as usize).saturating_sub(label_width + 1);
+        if bar_width == 0 || inner.height == 0 || frame_millis <= 0.0 {
+            return;
+        }
+        let col =
+            |millis: f64| ((millis / frame_millis * bar_width as f64) as usize).min(bar_width);
+
+        let end_label = fmt_millis(frame_millis, self.state.precision);
+        let mut lines = vec![Line::raw(format!(
+            "{:label_width$} 0{:>width$}",
+            "",
+            end_label,
+            width = bar_width.saturating_sub(1)
+        ))];
+        for (i, (start, child)) in children.iter().enumerate() {
+            let millis = child.total_duration().as_millis_f64();
+            let from = col(*start).min(bar_width - 1);
+            let to = col(start + millis).clamp(from + 1, bar_width);
+            let label: String = format!("{}/{}", child.target, child.span.name)
+                .chars()
+                .take(label_width)
+                .collect();
+            lines.push(Line::from(vec![
+                Span::raw(format!("{:label_width$} ", label)),
+                Span::raw(" ".repeat(from)),
+                Span::styled(
+                    "█".repeat(to - from),
+                    Style::default().fg(self.state.theme.palette_color(i)),
+                ),
+                Span::raw(format!(
+                    " {}",
+                    fmt_duration(child.total_duration(), self.state.precision)
+                )),
+            ]));
+        }
+        Paragraph::new(lines).render(inner, buf);
+    }
+
+    fn render_histogram(&self, area: Rect, buf: &mut Buffer) {
+        let histogram = self.duration_histogram(self.state.histogram_bins);
+        let (Some((first, _)), Some((last, _))) = (histogram.first(), histogram.last()) else {
+            Paragraph::new("No frames in the frame window").render(area, buf);
+            return;
+        };
+        // bin edges, not centers, at the ends of the axis
+        let half_bin = match histogram.get(1) {
+            Some((second, _)) => (second - first) / 2.0,
+            None => 0.5,
+        };
+        let (lower, upper) = (first - half_bin, last + half_bin);
+        let max_count = histogram.iter().fold(0.0, |max, (_, count)| count.max(max));
+        let precision = self.state.precision;
+
+        let x_axis = Axis::default()
+            .title("ms".fg(self.state.theme.axis_title))
+            .style(Style::default().fg(self.state.theme.axis))
+            .bounds([lower, upper])
+            .labels([fmt_millis(lower, precision), fmt_millis(upper, precision)]);
         let y_axis = Axis::default()
-            .title("ms (log scale)".red())
-            .style(Style::default().white())
-            .bounds([0.0, self.state.max.log10()])
-            .labels(["0.0", &max_str]);
-
-        // Create the chart and link all the parts together
-        let chart = Chart::new(datasets)
-            .block(Block::new().title("Chart"))
+            .title("count".fg(self.state.theme.axis_title))
+            .style(Style::default().fg(self.state.theme.axis))
+            .bounds([0.0, max_count])
+            .labels(["0".to_string(), max_count.to_string()]);
+        let datasets = vec![
+            Dataset::default()
+                .marker(symbols::Marker::HalfBlock)
+                .graph_type(GraphType::Bar)
+                .style(Style::default().fg(self.state.theme.total))
+                .data(&histogram),
+        ];
+        Chart::new(datasets)
+            .block(
+                Block::new()
+                    .title_style(self.title_style())
+                    .title(format!("Histogram - {} bins", self.state.histogram_bins)),
+            )
             .x_axis(x_axis)
             .y_axis(y_axis)
-            .render(frame_bar_area, buf);
+            .render(area, buf);
+    }
 
-        let detail_text = if let Some(detail_state) = &self.state.detail_state {
+    /// Style of the chart and panel titles.
+    fn title_style(&self) -> Style {
+        Style::default().fg(self.state.theme.title)
+    }
+
+    fn render_cdf(&self, area: Rect, buf: &mut Buffer) {
+        let cdf = self.duration_cdf();
+        let (Some((min, _)), Some((max, _))) = (cdf.first(), cdf.last()) else {
+            Paragraph::new("No frames in the frame window").render(area, buf);
+            return;
+        };
+        let (lower, upper) = if max > min {
+            (*min, *max)
+        } else {
+            (min - 0.5, max + 0.5)
+        };
+        let precision = self.state.precision;
+
+        // dashed vertical lines at the percentiles
+        let sorted: Vec<f64> = cdf.iter().skip(1).step_by(2).map(|(ms, _)| *ms).collect();
+        let markers: Vec<(String, Vec<(f64, f64)>)> = [("p50", 0.5), ("p95", 0.95), ("p99", 0.99)]
+            .into_iter()
+            .map(|(name, p)| {
+                let ms = stats::percentile(&sorted, p);
+                let dashes = (0..=10).map(|i| (ms, i as f64 / 10.0)).collect();
+                (format!("{} {}", name, fmt_millis(ms, precision)), dashes)
+            })
+            .collect();
+
+        let x_axis = Axis::default()
+            .title("ms".fg(self.state.theme.axis_title))
+            .style(Style::default().fg(self.state.theme.axis))
+            .bounds([lower, upper])
+            .labels([fmt_millis(lower, precision), fmt_millis(upper, precision)]);
+        let y_axis = Axis::default()
+            .title("fraction".fg(self.state.theme.axis_title))
+            .style(Style::default().fg(self.state.theme.axis))
+            .bounds([0.0, 1.0])
+            .labels(["0", "1"]);
+        let mut datasets = vec![
+            Dataset::default()
+                .marker(symbols::Marker::Braille)
+                .graph_type(GraphType::Line)
+                .style(Style::default().fg(self.state.theme.total))
+                .data(&cdf),
+        ];
+        for (name, dashes) in &markers {
+            datasets.push(
+                Dataset::default()
+                    .name(name.as_str())
+                    .marker(symbols::Marker::Dot)
+                    .graph_type(GraphType::Scatter)
+                    .style(Style::default().fg(self.state.theme.percentiles))
+                    .data(dashes),
+            );
+        }
+        Chart::new(datasets)
+            .block(
+                Block::new()
+                    .title_style(self.title_style())
+                    .title(format!("CDF - {} frames", sorted.len())),
+            )
+            .x_axis(x_axis)
+            .y_axis(y_axis)
+            .hidden_legend_constraints((Constraint::Ratio(1, 2), Constraint::Ratio(1, 2)))
+            .render(area, buf);
+    }
+
+    /// Text of the frame detail panel for the inspected frame.
+    fn detail_text(&self) -> String {
+        if let Some(detail_state) = &self.state.detail_state {
+            let frame_trace = &self.state.trace_data[detail_state.index];
             let mut s = String::new();
+            let total = frame_trace.trace.total_duration();
             s.push_str(&format!(
-                "frame id={}, {} - {:?}\n",
-                detail_state.frame_trace.trace.span.id.unwrap(),
-                detail_state.frame_trace.trace.target,
-                detail_state.frame_trace.trace.total_duration()
+                "frame id={}, {} - {} (busy {}, idle {})",
+                self.frame_label(detail_state.index),
+                frame_trace.trace.target,
+                fmt_duration(total, self.state.precision),
+                fmt_duration(frame_trace.trace.busy(), self.state.precision),
+                fmt_duration(frame_trace.trace.idle(), self.state.precision)
             ));
-            for child in &detail_state.frame_trace.child_traces {
+            if let Some(ratio) = stats::ratio(total.as_millis_f64(), self.state.median) {
+                s.push_str(&format!(" ({:.1}× median)", ratio));
+            }
+            s.push('\n');
+            for id in self.note_ids(detail_state.index) {
+                if let Some(note) = self.state.notes.get(&id) {
+                    s.push_str(&format!("note: {}\n", note));
+                }
+            }
+            if !self.state.compare_millis.is_empty() {
+                let frame_id = self.state.millis[detail_state.index].0;
+                let compared = self
+                    .state
+                    .compare_millis
+                    .iter()
+                    .find(|(x, _)| *x == frame_id);
+                s.push_str(&match compared {
+                    Some((_, millis)) => format!(
+                        "{}: {} ({})\n",
+                        self.state.compare_label,
+                        fmt_millis(*millis, self.state.precision),
+                        match stats::ratio(millis - total.as_millis_f64(), total.as_millis_f64()) {
+                            Some(change) => format!("{:+.1}%", change * 100.0),
+                            None => "-".to_string(),
+                        }
+                    ),
+                    None => format!("{}: no frame with this id\n", self.state.compare_label),
+                });
+            }
+            let child_millis: Vec<f64> = frame_trace
+                .child_traces
+                .iter()
+                .map(|child| child.total_duration().as_millis_f64())
+                .collect();
+            if let Some((mean, stddev)) = stats::mean_stddev(&child_millis) {
                 s.push_str(&format!(
-                    "  {}/{} - {:?}\n",
-                    child.target,
-                    child.span.name,
-                    child.total_duration()
+                    "children: {}, mean {}, stddev {}",
+                    child_millis.len(),
+                    fmt_millis(mean, self.state.precision),
+                    fmt_millis(stddev, self.state.precision)
                 ));
+                if let Some(cv) = stats::ratio(stddev, mean) {
+                    s.push_str(&format!(" (CV {:.2})", cv));
+                }
+                s.push('\n');
+            }
+            // depth first, indented by nesting depth
+            let mut stack: Vec<(usize, &trace::SpanNode)> = Vec::new();
+            let tree = frame_trace.span_tree(&detail_state.children);
+            stack.extend(tree.iter().rev().map(|node| (1, node)));
+            while let Some((depth, node)) = stack.pop() {
+                // children of a filtered out span move up in its place
+                if !self.level_matches(node.trace) {
+                    stack.extend(node.children.iter().rev().map(|child| (depth, child)));
+                    continue;
+                }
+                let duration = node.trace.total_duration();
+                s.push_str(&format!(
+                    "{}{:<5} {}/{} - {} (busy {}, idle {}, {})\n",
+                    "  ".repeat(depth),
+                    node.trace.level,
+                    node.trace.target,
+                    node.trace.span.name,
+                    fmt_duration(duration, self.state.precision),
+                    fmt_duration(node.trace.busy(), self.state.precision),
+                    fmt_duration(node.trace.idle(), self.state.precision),
+                    match stats::ratio(duration.as_secs_f64(), total.as_secs_f64()) {
+                        Some(share) => format!("{:.1}%", share * 100.0),
+                        None => "-".to_string(),
+                    }
+                ));
+                stack.extend(node.children.iter().rev().map(|child| (depth + 1, child)));
             }
             s
         } else {
             "No frame selected".to_string()
+        }
+    }
+
+    /// Per-frame table of the frames in the window, starting at its first frame.
+    fn render_frame_table(&self, area: Rect, buf: &mut Buffer) {
+        let precision = self.state.precision;
+        let start = self.state.frame_state.as_ref().map_or(0, |f| f.start) as f64;
+        let rows: Vec<Row> = self
+            .state
+            .trace_data
+            .iter()
+            .zip(&self.state.millis)
+            .filter(|(_, (x, _))| *x >= start)
+            .take(area.height as usize)
+            .map(|(frame_trace, (x, millis))| {
+                let slowest = frame_trace
+                    .child_traces
+                    .iter()
+                    .max_by_key(|child| child.total_duration());
+                Row::new([
+                    fmt_id(*x as u64, self.state.radix),
+                    fmt_millis(*millis, precision),
+                    fmt_duration(frame_trace.trace.busy(), precision),
+                    fmt_duration(frame_trace.trace.idle(), precision),
+                    frame_trace.child_traces.len().to_string(),
+                    slowest.map_or(String::new(), |child| {
+                        format!(
+                            "{}/{} - {}",
+                            child.target,
+                            child.span.name,
+                            fmt_duration(child.total_duration(), precision)
+                        )
+                    }),
+                ])
+            })
+            .collect();
+
+        let table = Table::new(
+            rows,
+            [
+                Constraint::Length(8),
+                Constraint::Length(12),
+                Constraint::Length(12),
+                Constraint::Length(12),
+                Constraint::Length(8),
+                Constraint::Min(20),
+            ],
+        )
+        .header(
+            Row::new([
+                "frame",
+                "total",
+                "busy",
+                "idle",
+                "children",
+                "slowest child",
+            ])
+            .style(Style::default().bold()),
+        )
+        .block(
+            Block::bordered()
+                .title_style(self.title_style())
+                .title("Frames"),
+        );
+        Widget::render(table, area, buf);
+    }
+}
+
+impl Widget for &mut App {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        let [frame_bar_area, detail_area, cmd_area] = Layout::vertical([
+            Constraint::Percentage(30),
+            Constraint::Min(10),
+            Constraint::Length(1),
+        ])
+        .areas(area);
+
+        let (start, end) = self.chart_x_bounds();
+        let max = self.y_axis_max();
+        let y_max = self.state.scale.apply(max);
+        self.state.chart_graph = None;
+
+        // compliance is a 0..1 fraction, stretched over the full y axis
+        let compliance_data: Vec<(f64, f64)> = match self.state.compliance_window {
+            Some(window) => {
+                let millis: Vec<f64> = self.state.millis.iter().map(|(_, ms)| *ms).collect();
+                stats::rolling_compliance(&millis, self.state.budget, window)
+                    .into_iter()
+                    .enumerate()
+                    .map(|(i, fraction)| (self.chart_x(i), fraction * y_max))
+                    .collect()
+            }
+            None => Vec::new(),
+        };
+
+        // frames per second, stretched so the fastest frame is at the top
+        let fps: Vec<(f64, f64)> = if self.state.show_fps {
+            self.state
+                .millis
+                .iter()
+                .enumerate()
+                .filter(|(_, (_, ms))| *ms > 0.0)
+                .map(|(i, (_, ms))| (self.chart_x(i), 1000.0 / ms))
+                .collect()
+        } else {
+            Vec::new()
+        };
+        let max_fps = fps.iter().fold(0.0, |max, (_, fps)| fps.max(max));
+        let fps_data: Vec<(f64, f64)> = fps
+            .iter()
+            .map(|(x, fps)| (*x, fps / max_fps * y_max))
+            .collect();
+
+        // frame bars, one dataset per frame target with a legend if there are several
+        let mut target_data: Vec<(&str, Vec<(f64, f64)>)> = Vec::new();
+        if self.state.view == View::Frames && self.state.target_colors.len() > 1 {
+            for (frame_trace, point) in self.state.trace_data.iter().zip(&self.state.data) {
+                let target = frame_trace.trace.target.as_str();
+                match target_data.iter_mut().find(|(t, _)| *t == target) {
+                    Some((_, points)) => points.push(*point),
+                    None => target_data.push((target, vec![*point])),
+                }
+            }
+            target_data.sort_by_key(|(target, _)| *target);
+        }
+        let mut datasets = if target_data.is_empty() {
+            vec![
+                Dataset::default()
+                    .marker(symbols::Marker::HalfBlock)
+                    .graph_type(GraphType::Bar)
+                    .style(Style::default().fg(match self.state.view {
+                        View::Interval => self.state.theme.interval,
+                        _ => self.state.theme.total,
+                    }))
+                    .data(&self.state.data),
+            ]
+        } else {
+            target_data
+                .iter()
+                .map(|(target, points)| {
+                    Dataset::default()
+                        .name(*target)
+                        .marker(symbols::Marker::HalfBlock)
+                        .graph_type(GraphType::Bar)
+                        .style(Style::default().fg(self.state.target_colors[*target]))
+                        .data(points)
+                })
+                .collect()
+        };
+        // faint lines behind everything else
+        let grid_lines: Vec<[(f64, f64); 2]> = match self.state.show_grid {
+            true => self
+                .grid_millis(max)
+                .into_iter()
+                .map(|ms| {
+                    let y = self.state.scale.apply(ms);
+                    [(start, y), (end, y)]
+                })
+                .collect(),
+            false => Vec::new(),
+        };
+        datasets.splice(
+            0..0,
+            grid_lines.iter().map(|line| {
+                Dataset::default()
+                    .marker(symbols::Marker::Braille)
+                    .graph_type(GraphType::Line)
+                    .style(Style::default().fg(self.state.theme.grid))
+                    .data(line)
+            }),
+        );
+        // frames over budget in red on top of the regular bars, with the budget line
+        let budget = self.state.budget;
+        let over_budget = self
+            .state
+            .millis
+            .iter()
+            .filter(|(_, ms)| *ms > budget)
+            .count();
+        // the budget is of whole frames, not of the drilled into target
+        let charts_budget =
+            self.state.view == View::Frames && self.state.drilldown_target.is_none();
+        let over_budget_data: Vec<(f64, f64)> = if charts_budget {
+            self.state
+                .millis
+                .iter()
+                .zip(&self.state.data)
+                .filter(|((_, ms), _)| *ms > budget)
+                .map(|(_, point)| *point)
+                .collect()
+        } else {
+            Vec::new()
+        };
+        let budget_y = self.state.scale.apply(budget);
+        let budget_line = [(start, budget_y), (end, budget_y)];
+        if charts_budget {
+            datasets.push(
+                Dataset::default()
+                    .marker(symbols::Marker::HalfBlock)
+                    .graph_type(GraphType::Bar)
+                    .style(Style::default().fg(self.state.theme.over_budget))
+                    .data(&over_budget_data),
+            );
+            datasets.push(
+                Dataset::default()
+                    .name(format!(
+                        "budget {}",
+                        fmt_millis(budget, self.state.precision)
+                    ))
+                    .marker(symbols::Marker::Braille)
+                    .graph_type(GraphType::Line)
+                    .style(Style::default().fg(self.state.theme.over_budget))
+                    .data(&budget_line),
+            );
+        }
+        let highlight_data: Vec<(f64, f64)> = match &self.state.highlight {
+            // one point per frame, in frame order
+            Some(highlight) if self.state.view == View::Wallclock => highlight
+                .frames
+                .iter()
+                .filter_map(|i| self.state.data.get(*i))
+                .copied()
+                .collect(),
+            Some(highlight) => {
+                let ids: HashSet<u64> = highlight
+                    .frames
+                    .iter()
+                    .map(|i| self.state.millis[*i].0 as u64)
+                    .collect();
+                self.state
+                    .data
+                    .iter()
+                    .filter(|(x, _)| ids.contains(&(*x as u64)))
+                    .copied()
+                    .collect()
+            }
+            None => Vec::new(),
+        };
+        if let Some(highlight) = &self.state.highlight {
+            datasets.push(
+                Dataset::default()
+                    .name(highlight.label.as_str())
+                    .marker(symbols::Marker::HalfBlock)
+                    .graph_type(GraphType::Bar)
+                    .style(Style::default().fg(highlight.color))
+                    .data(&highlight_data),
+            );
+        }
+        // a dot on the x axis in the middle of every gap
+        let gap_data: Vec<(f64, f64)> = match self.state.view {
+            View::Frames | View::Interval => self
+                .state
+                .gaps
+                .iter()
+                .map(|(first, last)| ((first + last) as f64 / 2.0, 0.0))
+                .collect(),
+            _ => Vec::new(),
+        };
+        if !gap_data.is_empty() {
+            datasets.push(
+                Dataset::default()
+                    .name("missing frames")
+                    .marker(symbols::Marker::Dot)
+                    .graph_type(GraphType::Scatter)
+                    .style(Style::default().fg(self.state.theme.gap))
+                    .data(&gap_data),
+            );
+        }
+        let selected_data: Vec<(f64, f64)> = match self.state.selected_frame {
+            Some(index) => {
+                let x = self.chart_x(index);
+                self.state
+                    .data
+                    .iter()
+                    .filter(|(data_x, _)| *data_x == x)
+                    .copied()
+                    .collect()
+            }
+            None => Vec::new(),
         };
+        if self.state.selected_frame.is_some() {
+            datasets.push(
+                Dataset::default()
+                    .marker(symbols::Marker::HalfBlock)
+                    .graph_type(GraphType::Bar)
+                    .style(Style::default().fg(self.state.theme.selected))
+                    .data(&selected_data),
+            );
+        }
+        if !self.state.compare_data.is_empty() {
+            datasets.push(
+                Dataset::default()
+                    .name(self.state.compare_label.as_str())
+                    .marker(symbols::Marker::Braille)
+                    .graph_type(GraphType::Line)
+                    .style(Style::default().fg(self.state.theme.compare))
+                    .data(&self.state.compare_data),
+            );
+        }
+        let average_data = match self.state.average_window {
+            Some(window) if self.state.view == View::Frames => self.moving_average(window),
+            _ => Vec::new(),
+        };
+        if !average_data.is_empty() {
+            datasets.push(
+                Dataset::default()
+                    .name(format!(
+                        "average of {} frames",
+                        self.state.average_window.unwrap_or(1)
+                    ))
+                    .marker(symbols::Marker::Braille)
+                    .graph_type(GraphType::Line)
+                    .style(Style::default().fg(self.state.theme.average))
+                    .data(&average_data),
+            );
+        }
+        if self.state.show_fps {
+            datasets.push(
+                Dataset::default()
+                    .name(self.fps_summary().unwrap_or_default())
+                    .marker(symbols::Marker::Braille)
+                    .graph_type(GraphType::Line)
+                    .style(Style::default().fg(self.state.theme.fps))
+                    .data(&fps_data),
+            );
+        }
+        if let Some(window) = self.state.compliance_window {
+            datasets.push(
+                Dataset::default()
+                    .name(format!("budget compliance (last {})", window))
+                    .marker(symbols::Marker::Braille)
+                    .graph_type(GraphType::Line)
+                    .style(Style::default().fg(self.state.theme.compliance))
+                    .data(&compliance_data),
+            );
+        }
+
+        if self.state.trace_data.is_empty() {
+            Paragraph::new("No frames found")
+                .block(Block::new().title_style(self.title_style()).title("Chart"))
+                .render(frame_bar_area, buf);
+        } else if let View::Areas | View::Stacked = self.state.view {
+            let busy_idle;
+            let bands = if self.state.view == View::Stacked {
+                busy_idle = self.busy_idle_bands();
+                &busy_idle
+            } else {
+                &self.state.target_areas
+            };
+            let len = self.state.trace_data.len();
+            let range = (
+                (start.max(0.0) as usize).min(len),
+                (end.max(0.0) as usize).min(len),
+            );
+            areas::StackedAreas {
+                bands,
+                range,
+                max,
+                title_color: self.state.theme.axis_title,
+                labels: (
+                    fmt_id(range.0 as u64, self.state.radix),
+                    fmt_id(range.1 as u64, self.state.radix),
+                ),
+            }
+            .render(frame_bar_area, buf);
+        } else if self.state.view == View::Cdf {
+            self.render_cdf(frame_bar_area, buf);
+        } else if self.state.view == View::Histogram {
+            self.render_histogram(frame_bar_area, buf);
+        } else {
+            let (chart_area, log_axis_area) = if let Scale::Dual = self.state.scale {
+                let [chart_area, log_axis_area] =
+                    Layout::horizontal([Constraint::Min(10), Constraint::Length(LOG_AXIS_WIDTH)])
+                        .areas(frame_bar_area);
+                (chart_area, Some(log_axis_area))
+            } else {
+                (frame_bar_area, None)
+            };
+
+            // Create the X axis and define its properties
+            let x_axis = Axis::default()
+                .style(Style::default().fg(self.state.theme.axis))
+                .bounds([start, end]);
+            let x_axis = if self.state.view == View::Wallclock {
+                x_axis
+                    .title("s".fg(self.state.theme.axis_title))
+                    .labels([format!("{:.1}", start), format!("{:.1}", end)])
+            } else {
+                x_axis
+                    .title("frame".fg(self.state.theme.axis_title))
+                    .labels([
+                        fmt_id(start as u64, self.state.radix),
+                        fmt_id(end as u64, self.state.radix),
+                    ])
+            };
+
+            let max_str = max.ceil().to_string();
+            let y_labels = match self.state.scale {
+                // the log scales start at 1ms, the labels between are drawn
+                // after the chart, wide enough for them
+                Scale::Log10 | Scale::Log2 => {
+                    let width = self
+                        .grid_millis(max)
+                        .iter()
+                        .map(|ms| fmt_millis(*ms, 0).len())
+                        .max()
+                        .unwrap_or(0);
+                    [
+                        format!("{:<width$}", fmt_millis(1.0, 0)),
+                        fmt_millis(max, 0),
+                    ]
+                }
+                Scale::Linear | Scale::Dual => ["0.0".to_string(), max_str],
+            };
+
+            // Create the Y axis and define its properties
+            let y_axis = Axis::default()
+                .title(self.state.scale.title().fg(self.state.theme.axis_title))
+                .style(Style::default().fg(self.state.theme.axis))
+                .bounds([0.0, y_max])
+                .labels(y_labels);
+
+            // Create the chart and link all the parts together
+            let frames = self.state.trace_data.len();
+            let capture = match self.state.capture_secs {
+                Some(secs) => format!("{:.1}s captured, {} frames", secs, frames),
+                None => format!("{} frames", frames),
+            };
+            Chart::new(datasets)
+                .block(Block::new().title_style(self.title_style()).title(
+                    match (self.state.view, self.state.merge) {
+                        (View::Frames, _) if let Some(target) = &self.state.drilldown_target => {
+                            format!("Chart - {}, time in {}", capture, target)
+                        }
+                        (View::Interval, _) => format!("Chart - {}, frame interval", capture),
+                        (View::Wallclock, _) => format!("Chart - {}, over time", capture),
+                        (_, 1) => format!("Chart - {}, {} over budget", capture, over_budget),
+                        (_, k) => format!(
+                            "Chart - {}, {} frames merged, {} over budget",
+                            capture, k, over_budget
+                        ),
+                    },
+                ))
+                .x_axis(x_axis)
+                .y_axis(y_axis)
+                .hidden_legend_constraints((Constraint::Ratio(1, 2), Constraint::Ratio(1, 2)))
+                .render(chart_area, buf);
+            self.state.chart_graph = graph_area(chart_area, buf);
+            if let Some(graph) = self.state.chart_graph {
+                match (self.state.scale, log_axis_area) {
+                    (_, Some(log_axis_area)) => self.render_log_axis(log_axis_area, graph, buf),
+                    (Scale::Log10 | Scale::Log2, _) => {
+                        self.render_log_labels(chart_area.x, graph, buf)
+                    }
+                    _ => {}
+                }
+            }
+        }
 
-        Paragraph::new(detail_text)
-            .block(Block::bordered().title("Frame Detail"))
-            .render(detail_area, buf);
+        let detail_text = self.detail_text();
 
-        Paragraph::new(self.state.input.as_str())
+        // keep the last line of the text at the bottom at most
+        let detail_lines = match &self.state.report {
+            Some(report) => report.text.lines().count(),
+            None => detail_text.lines().count(),
+        };
+        let visible_lines = detail_area.height.saturating_sub(2);
+        self.state.detail_scroll = self
+            .state
+            .detail_scroll
+            .min((detail_lines as u16).saturating_sub(visible_lines));
+        let scroll = (self.state.detail_scroll, 0);
+
+        if let Some(report) = &self.state.report {
+            Paragraph::new(report.text.as_str())
+                .block(
+                    Block::bordered()
+                        .title_style(self.title_style())
+                        .title(report.title.as_str()),
+                )
+                .scroll(scroll)
+                .render(detail_area, buf);
+        } else if self.state.detail_mode == DetailMode::Table {
+            self.render_frame_table(detail_area, buf);
+        } else if let (DetailMode::Donut, Some(detail_state)) =
+            (self.state.detail_mode, &self.state.detail_state)
+        {
+            self.render_target_breakdown(detail_state.index, detail_area, buf);
+        } else if let (DetailMode::Timeline, Some(detail_state)) =
+            (self.state.detail_mode, &self.state.detail_state)
+        {
+            self.render_timeline(detail_state.index, detail_area, buf);
+        } else {
+            Paragraph::new(detail_text)
+                .block(
+                    Block::bordered()
+                        .title_style(self.title_style())
+                        .title("Frame Detail"),
+                )
+                .scroll(scroll)
+                .render(detail_area, buf);
+        }
+
+        let input_line = match self.state.input_mode {
+            InputMode::Note => format!("note> {}", self.state.input),
+            _ if self.state.input.is_empty() => {
+                match (&self.state.message, &self.state.detail_state) {
+                    (Some(message), _) => message.clone(),
+                    (None, _) if let Some(error) = &self.state.load_error => error.clone(),
+                    (None, Some(detail_state)) => format!(
+                        "frame {} - {} into capture",
+                        self.frame_label(detail_state.index),
+                        fmt_millis(self.state.elapsed[detail_state.index], self.state.precision)
+                    ),
+                    (None, None) => String::new(),
+                }
+            }
+            _ => self.state.input.clone(),
+        };
+        // frame pacing on the right, if it fits next to the status
+        let pacing = self.pacing_stats();
+        if let InputMode::Normal = self.state.input_mode
+            && pacing.deltas > 0
+        {
+            let pacing_line = format!(
+                "pacing: delta stddev {}, {:.1}% jank",
+                fmt_millis(pacing.delta_stddev, self.state.precision),
+                pacing.jank * 100.0
+            );
+            let width = input_line.chars().count() + 2 + pacing_line.chars().count();
+            if width <= cmd_area.width as usize {
+                Paragraph::new(pacing_line)
+                    .right_aligned()
+                    .render(cmd_area, buf);
+            }
+        }
+        Paragraph::new(input_line)
             .style(match self.state.input_mode {
                 InputMode::Normal => Style::default(),
-                InputMode::Editing => Style::default().fg(Color::Yellow),
+                InputMode::Editing | InputMode::Note => Style::default().fg(self.state.theme.input),
             })
             .render(cmd_area, buf);
+
+        self.render_tooltip(frame_bar_area, buf);
+        if self.state.show_help {
+            help::HelpOverlay.render(area, buf);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn testdata(name: &str) -> std::path::PathBuf {
+        std::path::Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("testdata")
+            .join(name)
+    }
+
+    fn app(name: &str) -> App {
+        let path = testdata(name);
+        let (frames, _) =
+            trace::read_trace_file(&path, trace::DEFAULT_FRAME_SPAN, FrameWindow::default(), 1)
+                .unwrap();
+        App::new(
+            frames,
+            &Cli::parse_from([std::ffi::OsStr::new("terminus"), path.as_os_str()]),
+        )
+    }
+
+    #[test]
+    fn test_exclude_frames_without_ids() {
+        let mut app = app("lifecycle.json");
+        for frame_trace in &mut app.state.source_frames {
+            frame_trace.trace.span.id = None;
+        }
+        // ids fall back to the frame index
+        app.exec_exclude(Some((0, 0)));
+        assert_eq!(app.state.trace_data.len(), 1);
+        assert_eq!(app.state.message.as_deref(), Some("1 frames excluded"));
+    }
+
+    #[test]
+    fn test_restore_areas_session() {
+        let mut app = app("nested_spans.json");
+        app.apply_session(session::Session {
+            layout: layout::Layout {
+                view: View::Areas,
+                ..Default::default()
+            },
+            ..Default::default()
+        });
+        assert_eq!(app.state.view, View::Areas);
+        assert!(!app.state.target_areas.is_empty());
+    }
+
+    #[test]
+    fn test_session_round_trip() {
+        let mut saved = app("lifecycle.json");
+        saved.exec_exclude(Some((1, 1)));
+        saved.state.frame_state = Some(FrameState { start: 2, end: 2 });
+        saved.set_view(View::Areas);
+        saved.inspect_frame(0);
+        let json = serde_json::to_string(&saved.session()).unwrap();
+
+        let mut restored = app("lifecycle.json");
+        restored.apply_session(serde_json::from_str(&json).unwrap());
+        assert_eq!(
+            restored.state.message.as_deref(),
+            Some("restored the session")
+        );
+        assert_eq!(restored.state.trace_data.len(), 1);
+        assert_eq!(serde_json::to_string(&restored.session()).unwrap(), json);
+    }
+
+    #[test]
+    fn test_restore_stale_session() {
+        let mut app = app("lifecycle.json");
+        app.apply_session(session::Session {
+            layout: layout::Layout {
+                frame_range: Some((100, 200)),
+                ..Default::default()
+            },
+            inspected: Some(99),
+        });
+        assert_eq!(
+            app.state.message.as_deref(),
+            Some("restored the session without the frame window and frame 99, not in the trace")
+        );
+        assert!(app.state.frame_state.is_none());
+        assert!(app.state.detail_state.is_none());
     }
 }
@@ -0,0 +1,133 @@
+use std::fmt::Write as _;
+use std::io::{BufRead, BufReader, Write};
+use std::net::{IpAddr, TcpListener, TcpStream};
+use std::sync::{Arc, Mutex, PoisonError};
+use std::thread;
+use std::time::Duration;
+
+use crate::stats;
+
+/// Serves the latest frame statistics in the Prometheus text exposition
+/// format on every request, whatever the path.
+pub struct Metrics {
+    body: Arc<Mutex<String>>,
+}
+
+/// How long a scraper may take to send its request or read the response, the
+/// others wait for it on the one server thread.
+const TIMEOUT: Duration = Duration::from_secs(5);
+
+impl Metrics {
+    /// Starts the HTTP server thread on `port` of `addr`.
+    pub fn serve(addr: IpAddr, port: u16) -> Result<Metrics, String> {
+        let listener = TcpListener::bind((addr, port))
+            .map_err(|e| format!("can't serve metrics on {}:{}: {}", addr, port, e))?;
+        let body = Arc::new(Mutex::new(String::new()));
+        let shared = Arc::clone(&body);
+        thread::spawn(move || {
+            for stream in listener.incoming().flatten() {
+                let body = shared
+                    .lock()
+                    .unwrap_or_else(PoisonError::into_inner)
+                    .clone();
+                // a failing scraper must not take the endpoint down
+                let _ = respond(stream, &body);
+            }
+        });
+        Ok(Metrics { body })
+    }
+
+    /// Replaces the served statistics with those of `millis`, the frame
+    /// durations in ms.
+    pub fn update(&self, millis: &[f64], budget: f64) {
+        let mut sorted = millis.to_vec();
+        sorted.sort_by(|a, b| a.partial_cmp(b).unwrap());
+        let secs = |ms: f64| ms / 1000.0;
+
+        let mut body = String::new();
+        body.push_str("# HELP terminus_frame_duration_seconds Duration of the traced frames.\n");
+        body.push_str("# TYPE terminus_frame_duration_seconds summary\n");
+        for q in [0.5, 0.95, 0.99] {
+            let _ = writeln!(
+                body,
+                "terminus_frame_duration_seconds{{quantile=\"{}\"}} {}",
+                q,
+                secs(stats::percentile(&sorted, q))
+            );
+        }
+        let _ = writeln!(
+            body,
+            "terminus_frame_duration_seconds_sum {}",
+            secs(sorted.iter().sum())
+        );
+        let _ = writeln!(
+            body,
+            "terminus_frame_duration_seconds_count {}",
+            sorted.len()
+        );
+
+        body.push_str(
+            "# HELP terminus_frame_duration_max_seconds Duration of the slowest frame.\n",
+        );
+        body.push_str("# TYPE terminus_frame_duration_max_seconds gauge\n");
+        let _ = writeln!(
+            body,
+            "terminus_frame_duration_max_seconds {}",
+            secs(sorted.last().copied().unwrap_or(0.0))
+        );
+
+        let within = sorted.iter().filter(|ms| **ms <= budget).count();
+        let compliance = stats::ratio(within as f64, sorted.len() as f64).unwrap_or(1.0);
+        body.push_str(
+            "# HELP terminus_frame_budget_compliance Fraction of frames within the frame time budget.\n",
+        );
+        body.push_str("# TYPE terminus_frame_budget_compliance gauge\n");
+        let _ = writeln!(body, "terminus_frame_budget_compliance {}", compliance);
+
+        *self.body.lock().unwrap_or_else(PoisonError::into_inner) = body;
+    }
+}
+
+fn respond(mut stream: TcpStream, body: &str) -> std::io::Result<()> {
+    stream.set_read_timeout(Some(TIMEOUT))?;
+    stream.set_write_timeout(Some(TIMEOUT))?;
+    // skip the request up to the empty line ending its header
+    let mut reader = BufReader::new(&stream);
+    let mut line = String::new();
+    while reader.read_line(&mut line)? > 0 && line.trim_end() != "" {
+        line.clear();
+    }
+    write!(
+        stream,
+        "HTTP/1.1 200 OK\r\nContent-Type: text/plain; version=0.0.4\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        body.len(),
+        body
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_update() {
+        let metrics = Metrics {
+            body: Arc::new(Mutex::new(String::new())),
+        };
+        metrics.update(&[4.0, 1.0, 3.0, 2.0], 2.5);
+        let body = metrics.body.lock().unwrap().clone();
+        let lines: Vec<&str> = body.lines().filter(|l| !l.starts_with('#')).collect();
+        assert_eq!(
+            lines,
+            [
+                "terminus_frame_duration_seconds{quantile=\"0.5\"} 0.002",
+                "terminus_frame_duration_seconds{quantile=\"0.95\"} 0.004",
+                "terminus_frame_duration_seconds{quantile=\"0.99\"} 0.004",
+                "terminus_frame_duration_seconds_sum 0.01",
+                "terminus_frame_duration_seconds_count 4",
+                "terminus_frame_duration_max_seconds 0.004",
+                "terminus_frame_budget_compliance 0.5",
+            ]
+        );
+    }
+}
@@ -0,0 +1,81 @@
+use std::fmt::Write;
+
+use ratatui::buffer::{Buffer, Cell};
+use ratatui::style::{Color, Modifier};
+
+/// Renders the buffer as text, one line per row. With `ansi`, colors and
+/// modifiers are kept as SGR escape sequences.
+pub fn buffer_to_text(buf: &Buffer, ansi: bool) -> String {
+    let mut out = String::new();
+    for y in buf.area.top()..buf.area.bottom() {
+        let mut line = String::new();
+        let mut style: Option<(Color, Color, Modifier)> = None;
+        for x in buf.area.left()..buf.area.right() {
+            let cell = &buf[(x, y)];
+            if cell.skip {
+                continue;
+            }
+            if ansi {
+                let cell_style = (cell.fg, cell.bg, cell.modifier);
+                if style != Some(cell_style) {
+                    line.push_str(&sgr(cell));
+                    style = Some(cell_style);
+                }
+            }
+            line.push_str(cell.symbol());
+        }
+        if ansi {
+            line.push_str("\x1b[0m");
+        } else {
+            line.truncate(line.trim_end().len());
+        }
+        out.push_str(&line);
+        out.push('\n');
+    }
+    out
+}
+
+fn sgr(cell: &Cell) -> String {
+    let mut codes = String::from("\x1b[0");
+    for (modifier, code) in [
+        (Modifier::BOLD, 1),
+        (Modifier::DIM, 2),
+        (Modifier::ITALIC, 3),
+        (Modifier::UNDERLINED, 4),
+        (Modifier::REVERSED, 7),
+        (Modifier::CROSSED_OUT, 9),
+    ] {
+        if cell.modifier.contains(modifier) {
+            write!(codes, ";{}", code).unwrap();
+        }
+    }
+    push_color(&mut codes, cell.fg, 30);
+    push_color(&mut codes, cell.bg, 40);
+    codes.push('m');
+    codes
+}
+
+/// `base` is 30 for the foreground and 40 for the background.
+fn push_color(codes: &mut String, color: Color, base: u8) {
+    let _ = match color {
+        Color::Reset => Ok(()),
+        Color::Black => write!(codes, ";{}", base),
+        Color::Red => write!(codes, ";{}", base + 1),
+        Color::Green => write!(codes, ";{}", base + 2),
+        Color::Yellow => write!(codes, ";{}", base + 3),
+        Color::Blue => write!(codes, ";{}", base + 4),
+        Color::Magenta => write!(codes, ";{}", base + 5),
+        Color::Cyan => write!(codes, ";{}", base + 6),
+        Color::Gray => write!(codes, ";{}", base + 7),
+        Color::DarkGray => write!(codes, ";{}", base + 60),
+        Color::LightRed => write!(codes, ";{}", base + 61),
+        Color::LightGreen => write!(codes, ";{}", base + 62),
+        Color::LightYellow => write!(codes, ";{}", base + 63),
+        Color::LightBlue => write!(codes, ";{}", base + 64),
+        Color::LightMagenta => write!(codes, ";{}", base + 65),
+        Color::LightCyan => write!(codes, ";{}", base + 66),
+        Color::White => write!(codes, ";{}", base + 67),
+        Color::Rgb(r, g, b) => write!(codes, ";{};2;{};{};{}", base + 8, r, g, b),
+        Color::Indexed(i) => write!(codes, ";{};5;{}", base + 8, i),
+    };
+}
@@ -0,0 +1,26 @@
+use std::fs::File;
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+use crate::layout::Layout;
+
+/// The layout plus the inspected frame, saved with `:w` and restored with
+/// `--session`.
+#[derive(Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Session {
+    #[serde(flatten)]
+    pub layout: Layout,
+    pub inspected: Option<u64>,
+}
+
+pub fn save(path: &Path, session: &Session) -> Result<(), String> {
+    let file = File::create(path).map_err(|e| format!("session {}: {}", path.display(), e))?;
+    serde_json::to_writer_pretty(file, session).map_err(|e| e.to_string())
+}
+
+pub fn load(path: &Path) -> Result<Session, String> {
+    let file = File::open(path).map_err(|e| format!("session {}: {}", path.display(), e))?;
+    serde_json::from_reader(file).map_err(|e| format!("session {}: {}", path.display(), e))
+}
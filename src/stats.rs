@@ -0,0 +1,253 @@
+/// Median of an already sorted slice. Returns 0.0 for an empty slice.
+pub fn median(sorted: &[f64]) -> f64 {
+    let len = sorted.len();
+    if len == 0 {
+        return 0.0;
+    }
+    if len.is_multiple_of(2) {
+        (sorted[len / 2 - 1] + sorted[len / 2]) / 2.0
+    } else {
+        sorted[len / 2]
+    }
+}
+
+/// `value / base`, or `None` if base is zero.
+pub fn ratio(value: f64, base: f64) -> Option<f64> {
+    if base == 0.0 {
+        None
+    } else {
+        Some(value / base)
+    }
+}
+
+/// For each value, the fraction of the trailing `window` values (including
+/// itself) that are within `budget`.
+pub fn rolling_compliance(values: &[f64], budget: f64, window: usize) -> Vec<f64> {
+    let mut result = Vec::with_capacity(values.len());
+    let mut within = 0;
+    for (i, value) in values.iter().enumerate() {
+        if *value <= budget {
+            within += 1;
+        }
+        if i >= window && values[i - window] <= budget {
+            within -= 1;
+        }
+        let len = (i + 1).min(window);
+        result.push(within as f64 / len as f64);
+    }
+    result
+}
+
+pub struct ChangePoint {
+    /// index of the first value after the shift
+    pub index: usize,
+    pub before: f64,
+    pub after: f64,
+}
+
+/// minimum t-statistic between two segments to count as a shift
+const CHANGE_POINT_T: f64 = 8.0;
+/// minimum relative difference of the segment means to count as a shift
+const CHANGE_POINT_REL: f64 = 0.1;
+
+/// Finds points where the mean of `values` shifts permanently, using binary
+/// segmentation: the split maximising the difference of the means on both
+/// sides is kept if significant, and both halves are searched again.
+pub fn change_points(values: &[f64], min_segment: usize) -> Vec<ChangePoint> {
+    let mut prefix = Vec::with_capacity(values.len() + 1);
+    let mut prefix_sq = Vec::with_capacity(values.len() + 1);
+    prefix.push(0.0);
+    prefix_sq.push(0.0);
+    for v in values {
+        prefix.push(prefix.last().unwrap() + v);
+        prefix_sq.push(prefix_sq.last().unwrap() + v * v);
+    }
+
+    let mut splits = Vec::new();
+    split_segment(
+        &prefix,
+        &prefix_sq,
+        0,
+        values.len(),
+        min_segment.max(2),
+        &mut splits,
+    );
+    splits.sort();
+
+    let mut result = Vec::with_capacity(splits.len());
+    for (i, split) in splits.iter().enumerate() {
+        let lo = if i == 0 { 0 } else { splits[i - 1] };
+        let hi = splits.get(i + 1).copied().unwrap_or(values.len());
+        result.push(ChangePoint {
+            index: *split,
+            before: segment_mean(&prefix, lo, *split),
+            after: segment_mean(&prefix, *split, hi),
+        });
+    }
+    result
+}
+
+fn segment_mean(prefix: &[f64], lo: usize, hi: usize) -> f64 {
+    (prefix[hi] - prefix[lo]) / (hi - lo) as f64
+}
+
+fn segment_var(prefix: &[f64], prefix_sq: &[f64], lo: usize, hi: usize) -> f64 {
+    let n = (hi - lo) as f64;
+    let mean = segment_mean(prefix, lo, hi);
+    ((prefix_sq[hi] - prefix_sq[lo]) / n - mean * mean).max(0.0)
+}
+
+fn split_segment(
+    prefix: &[f64],
+    prefix_sq: &[f64],
+    lo: usize,
+    hi: usize,
+    min_segment: usize,
+    splits: &mut Vec<usize>,
+) {
+    if hi - lo < 2 * min_segment {
+        return;
+    }
+
+    let mut best: Option<(usize, f64)> = None;
+    for split in lo + min_segment..=hi - min_segment {
+        let (m1, m2) = (
+            segment_mean(prefix, lo, split),
+            segment_mean(prefix, split, hi),
+        );
+        let v1 = segment_var(prefix, prefix_sq, lo, split) / (split - lo) as f64;
+        let v2 = segment_var(prefix, prefix_sq, split, hi) / (hi - split) as f64;
+        let t = if v1 + v2 == 0.0 {
+            if m1 == m2 { 0.0 } else { f64::INFINITY }
+        } else {
+            (m1 - m2).abs() / (v1 + v2).sqrt()
+        };
+        let rel = (m1 - m2).abs() / m1.min(m2).max(f64::EPSILON);
+        if t >= CHANGE_POINT_T && rel >= CHANGE_POINT_REL && best.is_none_or(|(_, bt)| t > bt) {
+            best = Some((split, t));
+        }
+    }
+
+    if let Some((split, _)) = best {
+        splits.push(split);
+        split_segment(prefix, prefix_sq, lo, split, min_segment, splits);
+        split_segment(prefix, prefix_sq, split, hi, min_segment, splits);
+    }
+}
+
+/// Splits `values` into `buckets` consecutive groups of about equal size and
+/// returns the maximum of each, so spikes survive downsampling.
+pub fn bucket_max(values: &[f64], buckets: usize) -> Vec<f64> {
+    if values.is_empty() || buckets == 0 {
+        return Vec::new();
+    }
+    (0..buckets)
+        .map(|b| {
+            let lo = values.len() * b / buckets;
+            let hi = (values.len() * (b + 1) / buckets).max(lo + 1);
+            values[lo..hi.min(values.len())]
+                .iter()
+                .fold(f64::NEG_INFINITY, |max, v| max.max(*v))
+        })
+        .collect()
+}
+
+/// Mean and (population) standard deviation, `None` for no values.
+pub fn mean_stddev(values: &[f64]) -> Option<(f64, f64)> {
+    if values.is_empty() {
+        return None;
+    }
+    let n = values.len() as f64;
+    let mean = values.iter().sum::<f64>() / n;
+    let var = values.iter().map(|v| (v - mean) * (v - mean)).sum::<f64>() / n;
+    Some((mean, var.sqrt()))
+}
+
+/// The value above which values are spikes, the mean plus `k` standard
+/// deviations. `None` if there are no values or all are equal.
+pub fn spike_threshold(values: &[f64], k: f64) -> Option<f64> {
+    match mean_stddev(values) {
+        Some((mean, stddev)) if stddev > 0.0 => Some(mean + k * stddev),
+        _ => None,
+    }
+}
+
+/// Nearest-rank percentile `p` (0..=1) of an already sorted slice. Returns
+/// 0.0 for an empty slice.
+pub fn percentile(sorted: &[f64], p: f64) -> f64 {
+    if sorted.is_empty() {
+        return 0.0;
+    }
+    let rank = (p * sorted.len() as f64).ceil() as usize;
+    sorted[rank.clamp(1, sorted.len()) - 1]
+}
+
+/// Mean frames per second of frames taking `millis`, and the mean of the
+/// worst 1% of them, without frames of zero duration. `None` if there are
+/// no such frames.
+pub fn fps(millis: &[f64]) -> Option<(f64, f64)> {
+    let mut fps: Vec<f64> = millis
+        .iter()
+        .filter(|ms| **ms > 0.0)
+        .map(|ms| 1000.0 / ms)
+        .collect();
+    let (mean, _) = mean_stddev(&fps)?;
+    fps.sort_by(|a, b| a.partial_cmp(b).unwrap());
+    let worst = fps.len().div_ceil(100);
+    let (low, _) = mean_stddev(&fps[..worst])?;
+    Some((mean, low))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// `n` values alternating between `low` and `low + 1`.
+    fn noisy(low: f64, n: usize) -> impl Iterator<Item = f64> {
+        (0..n).map(move |i| low + (i % 2) as f64)
+    }
+
+    #[test]
+    fn test_percentile() {
+        let sorted = [1.0, 2.0, 3.0, 4.0, 5.0];
+        assert_eq!(percentile(&sorted, 0.0), 1.0);
+        assert_eq!(percentile(&sorted, 0.2), 1.0);
+        assert_eq!(percentile(&sorted, 0.5), 3.0);
+        assert_eq!(percentile(&sorted, 0.99), 5.0);
+        assert_eq!(percentile(&sorted, 1.0), 5.0);
+        assert_eq!(percentile(&[], 0.5), 0.0);
+    }
+
+    #[test]
+    fn test_rolling_compliance() {
+        let millis = [10.0, 20.0, 10.0, 20.0];
+        assert_eq!(rolling_compliance(&millis, 16.0, 1), [1.0, 0.0, 1.0, 0.0]);
+        // until the window is full it covers the values so far
+        assert_eq!(
+            rolling_compliance(&millis, 16.0, 10),
+            [1.0, 0.5, 2.0 / 3.0, 0.5]
+        );
+        assert_eq!(rolling_compliance(&millis, 16.0, 2), [1.0, 0.5, 0.5, 0.5]);
+    }
+
+    #[test]
+    fn test_change_points() {
+        let step: Vec<f64> = noisy(10.0, 40).chain(noisy(20.0, 40)).collect();
+        let points = change_points(&step, 10);
+        assert_eq!(points.len(), 1);
+        assert_eq!(points[0].index, 40);
+        assert_eq!(points[0].before, 10.5);
+        assert_eq!(points[0].after, 20.5);
+
+        let flat: Vec<f64> = noisy(10.0, 80).collect();
+        assert!(change_points(&flat, 10).is_empty());
+
+        // a step closer to the start than `min_segment` isn't split off
+        let early: Vec<f64> = noisy(10.0, 6).chain(noisy(20.0, 34)).collect();
+        assert!(change_points(&early, 10).is_empty());
+        let points = change_points(&early, 6);
+        assert_eq!(points.len(), 1);
+        assert_eq!(points[0].index, 6);
+        assert!(change_points(&step[..19], 10).is_empty());
+    }
+}
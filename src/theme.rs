@@ -0,0 +1,216 @@
+use std::collections::HashMap;
+use std::fs::File;
+use std::path::Path;
+
+use ratatui::style::Color;
+use serde::Deserialize;
+
+/// Colors used for rendering. Each metric keeps the same color in every view
+/// it appears in.
+pub struct Theme {
+    /// total frame duration
+    pub total: Color,
+    /// busy part of the frame duration
+    pub busy: Color,
+    /// idle part of the frame duration
+    pub idle: Color,
+    /// time between consecutive frames
+    pub interval: Color,
+    /// rolling budget compliance
+    pub compliance: Color,
+    /// frames matching a search
+    pub search: Color,
+    /// frame selected with j/k
+    pub selected: Color,
+    /// grid lines of the chart
+    pub grid: Color,
+    /// frame duration of the compared file
+    pub compare: Color,
+    /// frames over the frame time budget, and the budget line
+    pub over_budget: Color,
+    /// frames per second
+    pub fps: Color,
+    /// moving average of the frame duration
+    pub average: Color,
+    /// frames far above the mean duration
+    pub spike: Color,
+    /// percentile markers
+    pub percentiles: Color,
+    /// missing frame ids
+    pub gap: Color,
+    /// axis lines and labels
+    pub axis: Color,
+    /// axis titles
+    pub axis_title: Color,
+    /// titles of the chart and the panels
+    pub title: Color,
+    /// command line while typing a command or note
+    pub input: Color,
+    /// colors given to targets and stacked bands in turn, never empty
+    pub palette: Vec<Color>,
+}
+
+/// A color of a theme file, or a list of them for the palette.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum ThemeColor {
+    One(String),
+    Many(Vec<String>),
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Theme {
+            total: Color::Magenta,
+            busy: Color::LightMagenta,
+            idle: Color::Gray,
+            interval: Color::Cyan,
+            compliance: Color::Green,
+            search: Color::Yellow,
+            selected: Color::White,
+            grid: Color::DarkGray,
+            compare: Color::LightBlue,
+            over_budget: Color::Red,
+            fps: Color::LightGreen,
+            average: Color::LightYellow,
+            spike: Color::LightRed,
+            percentiles: Color::DarkGray,
+            gap: Color::LightCyan,
+            axis: Color::White,
+            axis_title: Color::Red,
+            title: Color::Reset,
+            input: Color::Yellow,
+            palette: vec![
+                Color::Magenta,
+                Color::Cyan,
+                Color::Yellow,
+                Color::Green,
+                Color::Blue,
+                Color::Red,
+                Color::LightMagenta,
+                Color::LightCyan,
+            ],
+        }
+    }
+}
+
+impl Theme {
+    /// The color of the `i`th target or band, the palette repeats.
+    pub fn palette_color(&self, i: usize) -> Color {
+        self.palette[i % self.palette.len()]
+    }
+
+    /// The default theme with the colors of a JSON file mapping the field
+    /// names above to color names ("blue", "lightred"), indices ("208") or
+    /// hex codes ("#ff8800") replaced. The palette is a list of them.
+    pub fn load(path: &Path) -> Result<Theme, String> {
+        let file = File::open(path).map_err(|e| format!("theme {}: {}", path.display(), e))?;
+        let colors: HashMap<String, ThemeColor> = serde_json::from_reader(file)
+            .map_err(|e| format!("theme {}: {}", path.display(), e))?;
+        let parse = |name: &str, color: &str| {
+            color.parse().map_err(|_| {
+                format!(
+                    "theme {}: invalid color '{}' for {}",
+                    path.display(),
+                    color,
+                    name
+                )
+            })
+        };
+        let mut theme = Theme::default();
+        for (name, color) in colors {
+            let color = match (name.as_str(), color) {
+                ("palette", ThemeColor::Many(colors)) if !colors.is_empty() => {
+                    theme.palette = colors
+                        .iter()
+                        .map(|color| parse(&name, color))
+                        .collect::<Result<_, _>>()?;
+                    continue;
+                }
+                ("palette", _) => {
+                    return Err(format!(
+                        "theme {}: the palette must be a list of colors",
+                        path.display()
+                    ));
+                }
+                (_, ThemeColor::One(color)) => color,
+                (_, ThemeColor::Many(_)) => {
+                    return Err(format!(
+                        "theme {}: {} must be a single color",
+                        path.display(),
+                        name
+                    ));
+                }
+            };
+            let field = match name.as_str() {
+                "total" => &mut theme.total,
+                "busy" => &mut theme.busy,
+                "idle" => &mut theme.idle,
+                "interval" => &mut theme.interval,
+                "compliance" => &mut theme.compliance,
+                "search" => &mut theme.search,
+                "selected" => &mut theme.selected,
+                "grid" => &mut theme.grid,
+                "compare" => &mut theme.compare,
+                "over_budget" => &mut theme.over_budget,
+                "fps" => &mut theme.fps,
+                "average" => &mut theme.average,
+                "spike" => &mut theme.spike,
+                "percentiles" => &mut theme.percentiles,
+                "gap" => &mut theme.gap,
+                "axis" => &mut theme.axis,
+                "axis_title" => &mut theme.axis_title,
+                "title" => &mut theme.title,
+                "input" => &mut theme.input,
+                _ => {
+                    return Err(format!(
+                        "theme {}: unknown color '{}'",
+                        path.display(),
+                        name
+                    ));
+                }
+            };
+            *field = parse(&name, &color)?;
+        }
+        Ok(theme)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn load(name: &str, json: &str) -> Result<Theme, String> {
+        let path = std::env::temp_dir().join(format!("theme-{}-{}.json", name, std::process::id()));
+        std::fs::write(&path, json).unwrap();
+        let theme = Theme::load(&path);
+        std::fs::remove_file(&path).unwrap();
+        theme
+    }
+
+    #[test]
+    fn test_load_overrides() {
+        let theme = load(
+            "overrides",
+            r##"{"total": "blue", "busy": "208", "idle": "#ff8800", "palette": ["red", "#000000"]}"##,
+        )
+        .unwrap();
+        assert_eq!(theme.total, Color::Blue);
+        assert_eq!(theme.busy, Color::Indexed(208));
+        assert_eq!(theme.idle, Color::Rgb(0xff, 0x88, 0x00));
+        assert_eq!(theme.interval, Theme::default().interval);
+        assert_eq!(theme.palette, [Color::Red, Color::Rgb(0, 0, 0)]);
+        assert_eq!(theme.palette_color(3), Color::Rgb(0, 0, 0));
+    }
+
+    #[test]
+    fn test_load_errors() {
+        let err = |name, json| load(name, json).err().unwrap();
+        assert!(err("unknown", r#"{"totl": "blue"}"#).ends_with("unknown color 'totl'"));
+        assert!(err("invalid", r#"{"total": "bleu"}"#).ends_with("invalid color 'bleu' for total"));
+        assert!(
+            err("palette", r#"{"palette": []}"#).ends_with("the palette must be a list of colors")
+        );
+        assert!(err("list", r#"{"total": ["blue"]}"#).ends_with("total must be a single color"));
+    }
+}
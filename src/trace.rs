@@ -1,11 +1,21 @@
+use std::cell::RefCell;
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::fmt;
 use std::fs::File;
-use std::io::{BufRead, BufReader};
-use std::path::Path;
+use std::io::{self, BufRead, BufReader, Read, Seek, SeekFrom};
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::thread;
 use std::time::Duration;
 
-use serde::{Deserialize, Deserializer, Serialize};
-use serde_json::from_str;
+use chrono::{DateTime, Utc};
+use flate2::bufread::GzDecoder;
+use serde::de::IgnoredAny;
+use serde::{Deserialize, Deserializer};
+use serde_json::{from_slice, from_str};
 
+use fundu_core::error::ParseError;
 use fundu_core::parse::Parser;
 use fundu_core::time::{Multiplier, TimeUnit, TimeUnitsLike};
 
@@ -27,13 +37,14 @@ use fundu_core::time::{Multiplier, TimeUnit, TimeUnitsLike};
 pub struct Fields {
     pub message: String,
 
+    /// missing on `new`/`enter`/`exit` events, see [`Lifecycles`]
     #[serde(rename = "time.busy")]
-    #[serde(deserialize_with = "deserialize_duration")]
-    pub time_busy: Duration,
+    #[serde(default, deserialize_with = "deserialize_duration")]
+    pub time_busy: Option<Duration>,
 
     #[serde(rename = "time.idle")]
-    #[serde(deserialize_with = "deserialize_duration")]
-    pub time_idle: Duration,
+    #[serde(default, deserialize_with = "deserialize_duration")]
+    pub time_idle: Option<Duration>,
 }
 
 #[derive(Deserialize, Clone)]
@@ -44,48 +55,837 @@ pub struct Span {
 
 #[derive(Clone, Deserialize)]
 pub struct Trace {
+    #[serde(default)]
+    pub timestamp: Option<DateTime<Utc>>,
+    /// `INFO`, `WARN`, `ERROR`, …
+    #[serde(default)]
+    pub level: String,
     pub target: String,
     pub fields: Fields,
     pub span: Span,
+    /// the enclosing spans, outermost first
+    #[serde(default)]
+    pub spans: Vec<Span>,
+    /// time between the `enter` and `exit` events of a span without
+    /// `time.busy`, see [`Lifecycles`]
+    #[serde(skip)]
+    pub entered: Option<Duration>,
 }
 
 impl Trace {
+    /// `time.busy`, or the time between `enter` and `exit` without it.
+    pub fn busy(&self) -> Duration {
+        self.fields.time_busy.or(self.entered).unwrap_or_default()
+    }
+
+    pub fn idle(&self) -> Duration {
+        self.fields.time_idle.unwrap_or_default()
+    }
+
     pub fn total_duration(&self) -> Duration {
-        self.fields.time_busy + self.fields.time_idle
+        self.busy() + self.idle()
+    }
+}
+
+/// Times spans from their `enter` and `exit` events, for captures written
+/// with span events but not `time.busy`/`time.idle` on close. Each enter/exit
+/// pair becomes one span, at the position of the exit. Other lines without
+/// durations (`new`, a plain `close`, log events) are dropped, and lines
+/// with durations pass through unchanged.
+#[derive(Default)]
+pub struct Lifecycles {
+    /// when the spans were entered, by span id and name
+    entered: HashMap<(Option<u64>, String), DateTime<Utc>>,
+}
+
+impl Lifecycles {
+    /// The span `trace` completes, if any.
+    pub fn push(&mut self, mut trace: Trace) -> Option<Trace> {
+        if trace.fields.time_busy.is_some() || trace.fields.time_idle.is_some() {
+            return Some(trace);
+        }
+        let key = (trace.span.id, trace.span.name.clone());
+        match trace.fields.message.as_str() {
+            "enter" => {
+                if let Some(timestamp) = trace.timestamp {
+                    self.entered.insert(key, timestamp);
+                }
+                None
+            }
+            "exit" => {
+                trace.entered = self
+                    .entered
+                    .remove(&key)
+                    .zip(trace.timestamp)
+                    .and_then(|(enter, exit)| (exit - enter).to_std().ok());
+                Some(trace)
+            }
+            _ => None,
+        }
     }
 }
 
+/// A child span with the spans nested in it.
+pub struct SpanNode<'a> {
+    pub trace: &'a Trace,
+    pub children: Vec<SpanNode<'a>>,
+}
+
 #[derive(Clone, Deserialize)]
 pub struct FrameTrace {
     pub trace: Trace,
     pub child_traces: Vec<Trace>,
+    /// where the children are in the trace file if they haven't been read
+    /// yet, see [`index_trace_file`], several for merged frames
+    #[serde(skip)]
+    pub unread_children: Vec<ChildLines>,
+    /// ids of the frames merged into this one, see [`merge_frames`]
+    #[serde(skip)]
+    pub merged_ids: Vec<u64>,
+}
+
+/// The lines of the children of a frame in its trace file.
+#[derive(Clone)]
+pub struct ChildLines {
+    pub path: Arc<Path>,
+    /// byte range of the lines between the previous frame span and this one
+    pub range: Range<u64>,
+}
+
+impl ChildLines {
+    /// Reads the children, skipping lines that aren't valid traces.
+    pub fn read(&self) -> Result<Vec<Trace>, TraceError> {
+        let name = self.path.display().to_string();
+        let error = TraceError::io(&name);
+        let mut file = File::open(&self.path).map_err(&error)?;
+        file.seek(SeekFrom::Start(self.range.start))
+            .map_err(&error)?;
+        let reader = BufReader::new(file.take(self.range.end - self.range.start));
+        let mut lifecycles = Lifecycles::default();
+        Ok(reader
+            .lines()
+            .map_while(Result::ok)
+            .filter_map(|line| from_str::<Trace>(&line).ok())
+            .filter_map(|trace| lifecycles.push(trace))
+            .collect())
+    }
+}
+
+/// Just the span of a trace line, to find the frame spans without
+/// deserializing every line in full.
+#[derive(Deserialize)]
+struct SpanOnly {
+    span: Span,
+}
+
+impl FrameTrace {
+    /// Reads the children that haven't been read yet, see
+    /// [`index_trace_file`].
+    pub fn read_children(&mut self) -> Result<(), TraceError> {
+        while let Some(lines) = self.unread_children.first() {
+            self.child_traces.extend(lines.read()?);
+            self.unread_children.remove(0);
+        }
+        Ok(())
+    }
+
+    /// The id of the frame span, or `index` (the position of the frame) for
+    /// frame spans without one.
+    pub fn id(&self, index: usize) -> u64 {
+        self.trace.span.id.unwrap_or(index as u64)
+    }
+
+    /// The children nested by their parent span, as given by the `spans`
+    /// chain of each child. `child_traces` holds every span in the frame, at
+    /// any depth; spans whose parent isn't among them are at the top level.
+    /// Siblings are in the order of their indices in `order`, which lists
+    /// every child once.
+    pub fn span_tree(&self, order: &[usize]) -> Vec<SpanNode<'_>> {
+        let ids: HashSet<u64> = self
+            .child_traces
+            .iter()
+            .filter_map(|child| child.span.id)
+            .collect();
+        let mut roots = Vec::new();
+        let mut children_of: HashMap<u64, Vec<usize>> = HashMap::new();
+        for &i in order {
+            let child = &self.child_traces[i];
+            let parent = child.spans.last().and_then(|parent| parent.id);
+            match parent {
+                Some(parent) if ids.contains(&parent) && child.span.id != Some(parent) => {
+                    children_of.entry(parent).or_default().push(i)
+                }
+                _ => roots.push(i),
+            }
+        }
+
+        fn build<'a>(
+            frame: &'a FrameTrace,
+            i: usize,
+            children_of: &mut HashMap<u64, Vec<usize>>,
+        ) -> SpanNode<'a> {
+            let trace = &frame.child_traces[i];
+            // taking the entry visits every span once, even with repeated ids
+            let children = trace
+                .span
+                .id
+                .and_then(|id| children_of.remove(&id))
+                .unwrap_or_default();
+            SpanNode {
+                trace,
+                children: children
+                    .into_iter()
+                    .map(|child| build(frame, child, children_of))
+                    .collect(),
+            }
+        }
+        roots
+            .into_iter()
+            .map(|i| build(self, i, &mut children_of))
+            .collect()
+    }
+
+    /// Summed duration of the children per target, largest first.
+    pub fn time_by_target(&self) -> Vec<(&str, Duration)> {
+        let mut totals: Vec<(&str, Duration)> = Vec::new();
+        for child in &self.child_traces {
+            match totals
+                .iter_mut()
+                .find(|(target, _)| *target == child.target)
+            {
+                Some((_, total)) => *total += child.total_duration(),
+                None => totals.push((&child.target, child.total_duration())),
+            }
+        }
+        totals.sort_by_key(|(_, total)| std::cmp::Reverse(*total));
+        totals
+    }
 }
 
-pub fn read_trace_file(file: &Path) -> Result<Vec<FrameTrace>, String> {
-    let file = File::open(file).map_err(|e| e.to_string())?;
-    let lines = BufReader::new(file).lines();
+/// A line of a trace file that couldn't be parsed: its 1-based number and
+/// the parse error.
+pub type SkippedLine = (usize, String);
+
+/// Why a trace couldn't be read.
+#[derive(Debug)]
+pub enum TraceError {
+    /// reading the file `name` (or stdin) failed
+    Io { name: String, source: io::Error },
+    /// an invalid trace line, numbered from 1
+    Json {
+        line: usize,
+        source: serde_json::Error,
+    },
+    /// an invalid duration, e.g. with an unknown unit
+    Duration(String),
+    /// the file `name` has no valid trace line, `first` is the first of its
+    /// `skipped` invalid lines unless it is empty
+    NoTraces {
+        name: String,
+        first: Option<SkippedLine>,
+        skipped: usize,
+    },
+    /// the file can't be read the way asked for, e.g. indexing stdin
+    Unsupported(String),
+}
 
-    let mut raw_traces = Vec::new();
-    for line in lines.flatten() {
-        let trace: Trace = from_str(&line).map_err(|e| e.to_string() + &line)?;
-        raw_traces.push(trace);
+impl TraceError {
+    /// Maps an IO error of the file `name`, for `map_err`.
+    fn io(name: &str) -> impl Fn(io::Error) -> TraceError + '_ {
+        move |source| TraceError::Io {
+            name: name.to_string(),
+            source,
+        }
     }
+}
 
+impl fmt::Display for TraceError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            TraceError::Io { name, source } => write!(f, "{}: {}", name, source),
+            TraceError::Json { line, source } => write!(f, "line {}: {}", line, source),
+            TraceError::Duration(msg) | TraceError::Unsupported(msg) => f.write_str(msg),
+            TraceError::NoTraces {
+                name,
+                first: Some((line, e)),
+                skipped,
+            } => write!(
+                f,
+                "{}: no valid trace lines, {} malformed lines skipped, first at line {}: {}",
+                name, skipped, line, e
+            ),
+            TraceError::NoTraces {
+                name, first: None, ..
+            } => write!(f, "{}: file is empty", name),
+        }
+    }
+}
+
+impl std::error::Error for TraceError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            TraceError::Io { source, .. } => Some(source),
+            TraceError::Json { source, .. } => Some(source),
+            _ => None,
+        }
+    }
+}
+
+/// Reads several trace files, e.g. rotated logs of one session, in parallel and
+/// concatenates their frames in the order of `paths`. Each file is grouped
+/// into frames on its own, so a frame must not span two files. The errors of
+/// skipped lines are prefixed with their file if there are more than one.
+/// With `index` the files are read with [`index_trace_file`]. `window` applies
+/// to each file on its own, `threads` to the reading of each file.
+pub fn read_trace_files(
+    paths: &[PathBuf],
+    index: bool,
+    frame_span: &str,
+    window: FrameWindow,
+    threads: usize,
+) -> Result<(Vec<FrameTrace>, Vec<SkippedLine>), TraceError> {
+    let read = |path: &Path| {
+        if index {
+            index_trace_file(path, frame_span, window)
+        } else {
+            read_trace_file(path, frame_span, window, threads)
+        }
+    };
+    if let [path] = paths {
+        return read(path);
+    }
+    let results: Vec<_> = thread::scope(|scope| {
+        let handles: Vec<_> = paths
+            .iter()
+            .map(|path| scope.spawn(move || read(path)))
+            .collect();
+        handles
+            .into_iter()
+            .map(|handle| {
+                handle
+                    .join()
+                    .unwrap_or_else(|panic| std::panic::resume_unwind(panic))
+            })
+            .collect()
+    });
+
+    let mut frames = Vec::new();
+    let mut skipped = Vec::new();
+    for (path, result) in paths.iter().zip(results) {
+        let (file_frames, file_skipped) = result?;
+        frames.extend(file_frames);
+        skipped.extend(
+            file_skipped
+                .into_iter()
+                .map(|(line, e)| (line, format!("{}: {}", path.display(), e))),
+        );
+    }
+    Ok((frames, skipped))
+}
+
+/// Combines every `k` consecutive frames into one frame with summed durations
+/// and the children of all of them. The merged frames are numbered from 0 and
+/// keep the ids of the frames in them.
+pub fn merge_frames(frames: &[FrameTrace], k: usize) -> Vec<FrameTrace> {
+    frames
+        .chunks(k.max(1))
+        .enumerate()
+        .map(|(i, chunk)| {
+            let last = &chunk[chunk.len() - 1];
+            let mut trace = last.trace.clone();
+            trace.span.id = Some(i as u64);
+            trace.fields.time_busy = Some(chunk.iter().map(|f| f.trace.busy()).sum());
+            trace.fields.time_idle = Some(chunk.iter().map(|f| f.trace.idle()).sum());
+            FrameTrace {
+                trace,
+                child_traces: chunk.iter().flat_map(|f| f.child_traces.clone()).collect(),
+                unread_children: chunk
+                    .iter()
+                    .flat_map(|f| f.unread_children.clone())
+                    .collect(),
+                merged_ids: chunk
+                    .iter()
+                    .enumerate()
+                    .map(|(j, f)| f.id(i * k.max(1) + j))
+                    .collect(),
+            }
+        })
+        .collect()
+}
+
+/// Reads the frames of a trace file, `-` reads stdin. Lines that aren't
+/// valid traces (e.g. a last line cut off by a crash) are skipped and
+/// returned with their error. Frames end at spans named `frame_span`, only
+/// those in `window` are kept and reading stops after them. The traces are
+/// parsed on `threads` threads.
+pub fn read_trace_file(
+    path: &Path,
+    frame_span: &str,
+    window: FrameWindow,
+    threads: usize,
+) -> Result<(Vec<FrameTrace>, Vec<SkippedLine>), TraceError> {
+    if path == Path::new(STDIN_PATH) {
+        let reader = decompress(io::stdin().lock(), "stdin")?;
+        return read_traces(reader, "stdin", frame_span, window, threads);
+    }
+    let name = path.display().to_string();
+    let file = File::open(path).map_err(TraceError::io(&name))?;
+    let reader = decompress(BufReader::new(file), &name)?;
+    read_traces(reader, &name, frame_span, window, threads)
+}
+
+/// Reads the frames of the traces of `reader`, one per line or all in a JSON
+/// array. `name` is used in errors.
+pub fn read_traces(
+    mut reader: impl BufRead,
+    name: &str,
+    frame_span: &str,
+    window: FrameWindow,
+    threads: usize,
+) -> Result<(Vec<FrameTrace>, Vec<SkippedLine>), TraceError> {
+    let mut grouper = FrameGrouper::new(frame_span, window);
+    let skipped = if is_json_array(&mut reader).map_err(TraceError::io(name))? {
+        // a single document, it can't skip bad traces
+        let traces: Vec<Trace> =
+            serde_json::from_reader(reader).map_err(|source| TraceError::Json {
+                line: source.line(),
+                source,
+            })?;
+        for trace in traces {
+            if !grouper.push(trace) {
+                break;
+            }
+        }
+        Vec::new()
+    } else {
+        let chunk_len = STREAM_CHUNK * threads;
+        read_stream(reader, name, threads, chunk_len, |trace| {
+            grouper.push(trace)
+        })?
+    };
+    if grouper.traces == 0 {
+        return Err(TraceError::NoTraces {
+            name: name.to_string(),
+            skipped: skipped.len(),
+            first: skipped.into_iter().next(),
+        });
+    }
+    Ok((grouper.frames, skipped))
+}
+
+/// Which frames of a trace file are read, set with --skip and --limit.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct FrameWindow {
+    /// frames left out at the start
+    pub skip: usize,
+    /// most frames read after them, all without a limit
+    pub limit: Option<usize>,
+}
+
+impl FrameWindow {
+    /// Whether the frame at `index` (counted from 0) is in the window.
+    fn contains(&self, index: usize) -> bool {
+        index >= self.skip && self.limit.is_none_or(|limit| index - self.skip < limit)
+    }
+}
+
+/// Groups traces into frames as they are read, like [`group_frames`], but
+/// keeps only the frames in a [`FrameWindow`] and their children.
+struct FrameGrouper<'a> {
+    frame_span: &'a str,
+    window: FrameWindow,
+    lifecycles: Lifecycles,
+    /// children of the next frame
+    pending: Vec<Trace>,
+    /// frames ended so far, in the window or not
+    seen: usize,
+    /// valid traces pushed
+    traces: usize,
+    frames: Vec<FrameTrace>,
+}
+
+impl<'a> FrameGrouper<'a> {
+    fn new(frame_span: &'a str, window: FrameWindow) -> Self {
+        FrameGrouper {
+            frame_span,
+            window,
+            lifecycles: Lifecycles::default(),
+            pending: Vec::new(),
+            seen: 0,
+            traces: 0,
+            frames: Vec::new(),
+        }
+    }
+
+    /// Adds the next trace, returns false once the window is full.
+    fn push(&mut self, trace: Trace) -> bool {
+        self.traces += 1;
+        let Some(trace) = self.lifecycles.push(trace) else {
+            return true;
+        };
+        let in_window = self.window.contains(self.seen);
+        if trace.span.name == self.frame_span {
+            if in_window {
+                self.frames.push(FrameTrace {
+                    trace,
+                    child_traces: std::mem::take(&mut self.pending),
+                    unread_children: Vec::new(),
+                    merged_ids: Vec::new(),
+                });
+            }
+            self.seen += 1;
+        } else if in_window {
+            self.pending.push(trace);
+        }
+        !self.is_full()
+    }
+
+    fn is_full(&self) -> bool {
+        self.window
+            .limit
+            .is_some_and(|limit| self.seen >= self.window.skip + limit)
+    }
+}
+
+/// Bytes of a stream parsed at once on each thread.
+const STREAM_CHUNK: usize = 1 << 20;
+
+/// Reads a stream of traces and parses it like [`parse_stream`], in chunks of
+/// at least `chunk_len` bytes ending before a line starting with `{` so no
+/// trace is split. Stops reading once `push` returns false.
+fn read_stream(
+    mut reader: impl BufRead,
+    name: &str,
+    threads: usize,
+    chunk_len: usize,
+    mut push: impl FnMut(Trace) -> bool,
+) -> Result<Vec<SkippedLine>, TraceError> {
+    let mut skipped = Vec::new();
+    let mut chunk = Vec::new();
+    // lines of the chunks parsed before, skipped lines are counted from 1
+    let mut lines_before = 0;
+    let mut more = true;
+    while more {
+        let line_start = chunk.len();
+        more = reader
+            .read_until(b'\n', &mut chunk)
+            .map_err(TraceError::io(name))?
+            > 0;
+        if more && !(line_start >= chunk_len && chunk[line_start] == b'{') {
+            continue;
+        }
+        let end = if more { line_start } else { chunk.len() };
+        let mut stopped = false;
+        let mut push_until = |trace| {
+            stopped = !push(trace);
+            !stopped
+        };
+        let chunk_skipped = if threads > 1 {
+            parse_stream_parallel(&chunk[..end], threads, &mut push_until)
+        } else {
+            parse_stream(&chunk[..end], &mut push_until)
+        };
+        skipped.extend(
+            chunk_skipped
+                .into_iter()
+                .map(|(line, e)| (lines_before + line, e)),
+        );
+        if stopped {
+            break;
+        }
+        lines_before += chunk[..end].iter().filter(|b| **b == b'\n').count();
+        chunk.drain(..end);
+    }
+    Ok(skipped)
+}
+
+/// Parses a stream of traces, one per line or pretty-printed over several.
+/// Traces that aren't valid are skipped, and so is invalid JSON up to the
+/// next line starting with `{`. Skipped traces are numbered by their first
+/// line. Stops once `push` returns false.
+fn parse_stream(bytes: &[u8], mut push: impl FnMut(Trace) -> bool) -> Vec<SkippedLine> {
+    take_unknown_units();
+    let mut skipped = Vec::new();
+    // line of the byte at `counted`, counted on as the stream is parsed
+    let (mut line, mut counted) = (1, 0);
+    let mut line_at = |pos: usize| {
+        line += bytes[counted..pos].iter().filter(|b| **b == b'\n').count();
+        counted = pos;
+        line
+    };
+    let mut pos = 0;
+    while pos < bytes.len() {
+        let mut values =
+            serde_json::Deserializer::from_slice(&bytes[pos..]).into_iter::<IgnoredAny>();
+        let mut start = pos;
+        let error = loop {
+            match values.next() {
+                None => break None,
+                Some(Ok(_)) => {
+                    let end = pos + values.byte_offset();
+                    let value = bytes[start..end].trim_ascii_start();
+                    match from_slice::<Trace>(value) {
+                        Ok(trace) => {
+                            if !push(trace) {
+                                return skipped;
+                            }
+                        }
+                        Err(e) => {
+                            let value_start = end - value.len();
+                            skipped.push((line_at(value_start), describe_error(e)));
+                        }
+                    }
+                    start = end;
+                }
+                Some(Err(e)) => break Some(e),
+            }
+        };
+        let Some(e) = error else { break };
+        let value_start = bytes.len() - bytes[start..].trim_ascii_start().len();
+        // continue at the next line that starts a trace
+        let resume = (value_start + 1..bytes.len())
+            .find(|i| bytes[i - 1] == b'\n' && bytes[*i] == b'{')
+            .unwrap_or(bytes.len());
+        let e = from_slice::<Trace>(bytes[value_start..resume].trim_ascii_end())
+            .err()
+            .unwrap_or(e);
+        skipped.push((line_at(value_start), describe_error(e)));
+        pos = resume;
+    }
+    skipped
+}
+
+/// Like [`parse_stream`], but parses `bytes` in about `threads` chunks at once
+/// and passes the traces to `push` in their order once all are parsed. The
+/// chunks start at lines starting with `{`, the top-level objects of
+/// pretty-printed traces too.
+fn parse_stream_parallel(
+    bytes: &[u8],
+    threads: usize,
+    mut push: impl FnMut(Trace) -> bool,
+) -> Vec<SkippedLine> {
+    let mut starts = vec![0];
+    for k in 1..threads {
+        let target = (bytes.len() * k / threads).max(starts[starts.len() - 1] + 1);
+        match (target..bytes.len()).find(|i| bytes[i - 1] == b'\n' && bytes[*i] == b'{') {
+            Some(start) => starts.push(start),
+            None => break,
+        }
+    }
+    let ends = starts.iter().skip(1).copied().chain([bytes.len()]);
+    let chunks: Vec<&[u8]> = starts
+        .iter()
+        .zip(ends)
+        .map(|(start, end)| &bytes[*start..end])
+        .collect();
+    let parsed: Vec<(Vec<Trace>, Vec<SkippedLine>)> = thread::scope(|scope| {
+        let handles: Vec<_> = chunks
+            .iter()
+            .map(|chunk| {
+                scope.spawn(move || {
+                    let mut traces = Vec::new();
+                    let skipped = parse_stream(chunk, |trace| {
+                        traces.push(trace);
+                        true
+                    });
+                    (traces, skipped)
+                })
+            })
+            .collect();
+        handles
+            .into_iter()
+            .map(|handle| {
+                handle
+                    .join()
+                    .unwrap_or_else(|panic| std::panic::resume_unwind(panic))
+            })
+            .collect()
+    });
+
+    let mut skipped = Vec::new();
+    // lines before the chunk, its skipped lines are counted from 1
+    let mut lines_before = 0;
+    for (chunk, (traces, chunk_skipped)) in chunks.iter().zip(parsed) {
+        skipped.extend(
+            chunk_skipped
+                .into_iter()
+                .map(|(line, e)| (lines_before + line, e)),
+        );
+        for trace in traces {
+            if !push(trace) {
+                return skipped;
+            }
+        }
+        lines_before += chunk.iter().filter(|b| **b == b'\n').count();
+    }
+    skipped
+}
+
+/// The error of a trace that couldn't be parsed, listing the unknown duration
+/// units it had if any.
+pub fn describe_error(e: serde_json::Error) -> String {
+    let unknown = take_unknown_units();
+    let mut msg = e.to_string();
+    if !unknown.is_empty() {
+        let units: Vec<String> = unknown.into_iter().collect();
+        msg.push_str(&format!(
+            ", unknown duration units: {} (known: ns, µs, us, ms, s, m, h, d, w)",
+            units.join(", ")
+        ));
+    }
+    msg
+}
+
+/// Whether the traces in `reader` are a JSON array rather than one per line,
+/// going by the first non-whitespace byte in its buffer.
+fn is_json_array(reader: &mut impl BufRead) -> io::Result<bool> {
+    let buf = reader.fill_buf()?;
+    Ok(buf.iter().find(|b| !b.is_ascii_whitespace()) == Some(&b'['))
+}
+
+/// Reads only the frame spans of a trace file and where their children are,
+/// for captures too large to keep in memory. The children are read with
+/// [`ChildLines::read`] once needed. Since that seeks in the file, gzipped
+/// files and stdin can't be indexed, and the traces must be one per line.
+pub fn index_trace_file(
+    path: &Path,
+    frame_span: &str,
+    window: FrameWindow,
+) -> Result<(Vec<FrameTrace>, Vec<SkippedLine>), TraceError> {
+    let name = path.display().to_string();
+    if path == Path::new(STDIN_PATH) {
+        return Err(TraceError::Unsupported("can't index stdin".to_string()));
+    }
+    let file = File::open(path).map_err(TraceError::io(&name))?;
+    let mut reader = BufReader::new(file);
+    if reader
+        .fill_buf()
+        .map_err(TraceError::io(&name))?
+        .starts_with(&GZIP_MAGIC)
+    {
+        return Err(TraceError::Unsupported(format!(
+            "{}: can't index a gzipped file",
+            name
+        )));
+    }
+    if is_json_array(&mut reader).map_err(TraceError::io(&name))? {
+        return Err(TraceError::Unsupported(format!(
+            "{}: can't index a JSON array",
+            name
+        )));
+    }
+
+    let path: Arc<Path> = Arc::from(path);
+    let mut frames = Vec::new();
+    let mut skipped = Vec::new();
+    let mut valid_lines = 0;
+    let mut line = Vec::new();
+    let mut offset = 0;
+    let mut children_start = 0;
+    let mut lifecycles = Lifecycles::default();
+    // frame spans seen, in the window or not
+    let mut seen = 0;
+    for i in 1.. {
+        line.clear();
+        let read = reader
+            .read_until(b'\n', &mut line)
+            .map_err(TraceError::io(&name))?;
+        if read == 0 {
+            break;
+        }
+        let line_start = offset;
+        offset += read as u64;
+        // only frame spans are deserialized in full
+        let trace = from_slice::<SpanOnly>(&line).and_then(|probe| {
+            (probe.span.name == frame_span)
+                .then(|| from_slice::<Trace>(&line))
+                .transpose()
+        });
+        match trace {
+            Ok(trace) => {
+                valid_lines += 1;
+                if let Some(trace) = trace.and_then(|trace| lifecycles.push(trace)) {
+                    if window.contains(seen) {
+                        frames.push(FrameTrace {
+                            trace,
+                            child_traces: Vec::new(),
+                            unread_children: vec![ChildLines {
+                                path: path.clone(),
+                                range: children_start..line_start,
+                            }],
+                            merged_ids: Vec::new(),
+                        });
+                    }
+                    seen += 1;
+                    children_start = offset;
+                    if window
+                        .limit
+                        .is_some_and(|limit| seen >= window.skip + limit)
+                    {
+                        break;
+                    }
+                }
+            }
+            Err(e) => skipped.push((i, e.to_string())),
+        }
+    }
+    if valid_lines == 0 {
+        return Err(TraceError::NoTraces {
+            name,
+            skipped: skipped.len(),
+            first: skipped.into_iter().next(),
+        });
+    }
+    Ok((frames, skipped))
+}
+
+/// Name of the span that encloses one frame, unless set with --frame-span.
+pub const DEFAULT_FRAME_SPAN: &str = "frame";
+
+/// first bytes of a gzip stream
+const GZIP_MAGIC: [u8; 2] = [0x1f, 0x8b];
+
+/// Trace file name meaning stdin.
+pub const STDIN_PATH: &str = "-";
+
+/// Decompresses `reader` if it is gzipped, `name` is used in errors.
+fn decompress(
+    mut reader: impl BufRead + 'static,
+    name: &str,
+) -> Result<Box<dyn BufRead>, TraceError> {
+    let gzipped = reader
+        .fill_buf()
+        .map_err(TraceError::io(name))?
+        .starts_with(&GZIP_MAGIC);
+    if gzipped {
+        Ok(Box::new(BufReader::new(GzDecoder::new(reader))))
+    } else {
+        Ok(Box::new(reader))
+    }
+}
+
+/// Groups traces into frames: every trace before a frame span (a span named
+/// `frame_span`) belongs to that frame. Traces after the last frame span are
+/// left in `pending` and become children of the next frame of a later call.
+pub fn group_frames(
+    traces: Vec<Trace>,
+    pending: &mut Vec<Trace>,
+    frame_span: &str,
+) -> Vec<FrameTrace> {
     let mut result = Vec::new();
-    let mut child_traces = Vec::new();
-    for trace in raw_traces {
-        if trace.span.name == "frame" {
+    for trace in traces {
+        if trace.span.name == frame_span {
             result.push(FrameTrace {
                 trace,
-                child_traces,
+                child_traces: std::mem::take(pending),
+                unread_children: Vec::new(),
+                merged_ids: Vec::new(),
             });
-            child_traces = Vec::new();
         } else {
-            child_traces.push(trace);
+            pending.push(trace);
         }
     }
-
-    Ok(result)
+    result
 }
 
 struct TimeUnits {}
@@ -99,7 +899,8 @@ impl TimeUnitsLike for TimeUnits {
     fn get(&self, identifier: &str) -> Option<(TimeUnit, Multiplier)> {
         match identifier {
             "ns" => Some((TimeUnit::NanoSecond, Multiplier(1, 0))),
-            "µs" => Some((TimeUnit::MicroSecond, Multiplier(1, 0))),
+            // "us" for writers that avoid non-ASCII output
+            "µs" | "us" => Some((TimeUnit::MicroSecond, Multiplier(1, 0))),
             "ms" => Some((TimeUnit::MilliSecond, Multiplier(1, 0))),
             "s" => Some((TimeUnit::Second, Multiplier(1, 0))),
             "m" => Some((TimeUnit::Minute, Multiplier(1, 0))),
@@ -114,13 +915,404 @@ impl TimeUnitsLike for TimeUnits {
 const DURATION_PARSER: Parser = Parser::new();
 const TIME_UNITS: TimeUnits = TimeUnits {};
 
-fn deserialize_duration<'de, D>(deserializer: D) -> Result<Duration, D::Error>
+/// Parses a duration in the format `tracing` uses for `time.busy`/`time.idle`, e.g. `2.93ms`.
+/// A comma as decimal separator (`2,93ms`), as written by some locales, is accepted too.
+pub fn parse_duration(s: &str) -> Result<Duration, TraceError> {
+    let normalized;
+    let s = if s.contains(',') && !s.contains('.') {
+        normalized = s.replace(',', ".");
+        &normalized
+    } else {
+        s
+    };
+    let duration = DURATION_PARSER
+        .parse(s, &TIME_UNITS, None, None)
+        .map_err(|e| match e {
+            ParseError::TimeUnit(pos, _) => {
+                let unit = s.get(pos..).unwrap_or(s).trim().to_string();
+                let msg = format!("unknown duration unit '{}' in '{}'", unit, s);
+                UNKNOWN_UNITS.with_borrow_mut(|units| units.insert(unit));
+                TraceError::Duration(msg)
+            }
+            e => TraceError::Duration(e.to_string()),
+        })?;
+    // the parser saturates on overflow instead of failing
+    if duration.abs() == fundu_core::time::Duration::MAX {
+        return Err(TraceError::Duration(format!(
+            "duration '{}' out of range",
+            s
+        )));
+    }
+    duration
+        .try_into()
+        .map_err(|e| TraceError::Duration(format!("duration '{}' out of range: {}", s, e)))
+}
+
+thread_local! {
+    /// duration units that failed to parse, collected per reading thread
+    static UNKNOWN_UNITS: RefCell<BTreeSet<String>> = const { RefCell::new(BTreeSet::new()) };
+    /// durations parsed on this thread by their text, most of them recur
+    static DURATION_CACHE: RefCell<HashMap<String, Duration>> = RefCell::new(HashMap::new());
+}
+
+/// Durations kept in the cache of a thread before it is emptied.
+const DURATION_CACHE_SIZE: usize = 1024;
+
+/// [`parse_duration`] with the valid durations cached per thread. Invalid ones
+/// are parsed again each time to collect their unknown units.
+fn parse_duration_cached(s: &str) -> Result<Duration, TraceError> {
+    if let Some(duration) = DURATION_CACHE.with_borrow(|cache| cache.get(s).copied()) {
+        return Ok(duration);
+    }
+    let duration = parse_duration(s)?;
+    DURATION_CACHE.with_borrow_mut(|cache| {
+        if cache.len() >= DURATION_CACHE_SIZE {
+            cache.clear();
+        }
+        cache.insert(s.to_string(), duration);
+    });
+    Ok(duration)
+}
+
+/// Returns and clears the unknown duration units seen on this thread.
+fn take_unknown_units() -> BTreeSet<String> {
+    UNKNOWN_UNITS.take()
+}
+
+fn deserialize_duration<'de, D>(deserializer: D) -> Result<Option<Duration>, D::Error>
 where
     D: Deserializer<'de>,
 {
     let buf = String::deserialize(deserializer)?;
-    let duration = DURATION_PARSER
-        .parse(&buf, &TIME_UNITS, None, None)
-        .map_err(serde::de::Error::custom)?;
-    Ok(duration.try_into().unwrap())
+    parse_duration_cached(&buf)
+        .map(Some)
+        .map_err(serde::de::Error::custom)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn testdata(name: &str) -> std::path::PathBuf {
+        Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("testdata")
+            .join(name)
+    }
+
+    #[test]
+    fn test_comma_decimal_durations() {
+        let (frames, _) = read_trace_file(
+            &testdata("comma_decimal.json"),
+            DEFAULT_FRAME_SPAN,
+            FrameWindow::default(),
+            1,
+        )
+        .unwrap();
+        assert_eq!(frames.len(), 1);
+        let frame = &frames[0];
+        assert_eq!(frame.trace.busy(), Duration::from_micros(6640));
+        assert_eq!(frame.trace.idle(), Duration::from_micros(7760));
+        assert_eq!(frame.child_traces.len(), 1);
+        assert_eq!(frame.child_traces[0].busy(), Duration::from_micros(2930));
+    }
+
+    #[test]
+    fn test_microsecond_units() {
+        let fields: Fields =
+            from_str(r#"{"message":"close","time.busy":"500µs","time.idle":"3us"}"#).unwrap();
+        assert_eq!(fields.time_busy, Some(Duration::from_micros(500)));
+        assert_eq!(fields.time_idle, Some(Duration::from_micros(3)));
+    }
+
+    #[test]
+    fn test_overflowing_duration() {
+        let result = from_str::<Fields>(
+            r#"{"message":"close","time.busy":"99999999999999999w","time.idle":"0s"}"#,
+        );
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_cached_durations() {
+        for s in ["16.67ms", "2,93ms", "0ns", "16.67ms", "3xs", "3xs"] {
+            assert_eq!(
+                parse_duration_cached(s).ok(),
+                parse_duration(s).ok(),
+                "{}",
+                s
+            );
+        }
+        // invalid durations aren't cached, their units are collected each time
+        take_unknown_units();
+        assert!(parse_duration_cached("3xs").is_err());
+        assert!(take_unknown_units().contains("xs"));
+        for i in 0..DURATION_CACHE_SIZE * 2 {
+            parse_duration_cached(&format!("{}ns", i)).unwrap();
+        }
+        assert!(DURATION_CACHE.with_borrow(HashMap::len) <= DURATION_CACHE_SIZE);
+    }
+
+    /// Run with `cargo test --release -- --ignored --nocapture bench_cached`.
+    #[test]
+    #[ignore]
+    fn bench_cached_durations() {
+        // the busy and idle times of a synthetic file, few distinct ones
+        let durations: Vec<String> = (0..400_000)
+            .map(|i| match i % 2 {
+                0 => format!("{}.{}ms", i % 17, i % 100),
+                _ => format!("{}µs", i % 50),
+            })
+            .collect();
+        let time = |parse: fn(&str) -> Result<Duration, TraceError>| {
+            let start = std::time::Instant::now();
+            for s in &durations {
+                parse(s).unwrap();
+            }
+            start.elapsed()
+        };
+        let uncached = time(parse_duration);
+        let cached = time(parse_duration_cached);
+        println!(
+            "{} durations: uncached {:?}, cached {:?}",
+            durations.len(),
+            uncached,
+            cached
+        );
+    }
+
+    #[test]
+    fn test_trace_errors() {
+        assert!(matches!(
+            read_trace_file(
+                &testdata("missing.json"),
+                DEFAULT_FRAME_SPAN,
+                FrameWindow::default(),
+                1
+            ),
+            Err(TraceError::Io { .. })
+        ));
+        assert!(matches!(
+            index_trace_file(
+                Path::new(STDIN_PATH),
+                DEFAULT_FRAME_SPAN,
+                FrameWindow::default()
+            ),
+            Err(TraceError::Unsupported(_))
+        ));
+        assert!(matches!(
+            parse_duration("3fortnights"),
+            Err(TraceError::Duration(_))
+        ));
+    }
+
+    #[test]
+    fn test_lifecycle_events() {
+        let path = testdata("lifecycle.json");
+        let (frames, skipped) =
+            read_trace_file(&path, DEFAULT_FRAME_SPAN, FrameWindow::default(), 1).unwrap();
+        assert!(skipped.is_empty());
+        assert_eq!(frames.len(), 2);
+        assert_eq!(
+            frames[0].trace.total_duration(),
+            Duration::from_micros(6640)
+        );
+        assert_eq!(frames[0].child_traces.len(), 1);
+        assert_eq!(
+            frames[0].child_traces[0].total_duration(),
+            Duration::from_micros(2930)
+        );
+        assert_eq!(frames[1].trace.total_duration(), Duration::from_millis(3));
+
+        let (indexed, _) =
+            index_trace_file(&path, DEFAULT_FRAME_SPAN, FrameWindow::default()).unwrap();
+        assert_eq!(indexed.len(), 2);
+        let children = indexed[0].unread_children[0].read().unwrap();
+        assert_eq!(children.len(), 1);
+    }
+
+    #[test]
+    fn test_json_array() {
+        let (frames, skipped) = read_trace_file(
+            &testdata("array.json"),
+            DEFAULT_FRAME_SPAN,
+            FrameWindow::default(),
+            1,
+        )
+        .unwrap();
+        let (lines, _) = read_trace_file(
+            &testdata("comma_decimal.json"),
+            DEFAULT_FRAME_SPAN,
+            FrameWindow::default(),
+            1,
+        )
+        .unwrap();
+        assert!(skipped.is_empty());
+        assert_eq!(frames.len(), lines.len());
+        assert_eq!(frames[0].child_traces.len(), lines[0].child_traces.len());
+        assert_eq!(
+            frames[0].trace.total_duration(),
+            lines[0].trace.total_duration()
+        );
+    }
+
+    #[test]
+    fn test_pretty_printed_traces() {
+        let (frames, skipped) = read_trace_file(
+            &testdata("pretty.json"),
+            DEFAULT_FRAME_SPAN,
+            FrameWindow::default(),
+            1,
+        )
+        .unwrap();
+        assert_eq!(frames.len(), 1);
+        assert_eq!(frames[0].child_traces.len(), 1);
+        assert_eq!(frames[0].trace.busy(), Duration::from_micros(6640));
+        let lines: Vec<usize> = skipped.iter().map(|(line, _)| *line).collect();
+        assert_eq!(lines, [20, 36]);
+    }
+
+    #[test]
+    fn test_parallel_parsing() {
+        let files = [
+            ("tick_spans.json", "tick"),
+            ("pretty.json", DEFAULT_FRAME_SPAN),
+            ("lifecycle.json", DEFAULT_FRAME_SPAN),
+        ];
+        for (name, frame_span) in files {
+            let read = |threads| {
+                read_trace_file(&testdata(name), frame_span, FrameWindow::default(), threads)
+                    .unwrap()
+            };
+            let (frames, skipped) = read(1);
+            assert!(!frames.is_empty(), "{}", name);
+            for threads in [2, 3, 8] {
+                let (parallel, parallel_skipped) = read(threads);
+                assert_eq!(parallel.len(), frames.len(), "{}", name);
+                for (a, b) in parallel.iter().zip(&frames) {
+                    assert_eq!(a.trace.span.id, b.trace.span.id);
+                    assert_eq!(a.child_traces.len(), b.child_traces.len());
+                    assert_eq!(a.trace.total_duration(), b.trace.total_duration());
+                }
+                assert_eq!(parallel_skipped, skipped, "{}", name);
+            }
+        }
+    }
+
+    #[test]
+    fn test_streamed_chunks() {
+        let file = || BufReader::new(File::open(testdata("pretty.json")).unwrap());
+        for threads in [1, 2] {
+            let mut frames = 0;
+            let skipped = read_stream(file(), "pretty.json", threads, 1, |trace| {
+                frames += usize::from(trace.span.name == DEFAULT_FRAME_SPAN);
+                true
+            })
+            .unwrap();
+            assert_eq!(frames, 1);
+            let lines: Vec<usize> = skipped.iter().map(|(line, _)| *line).collect();
+            assert_eq!(lines, [20, 36]);
+        }
+
+        // a full window stops reading before the end
+        struct Unreadable;
+        impl Read for Unreadable {
+            fn read(&mut self, _: &mut [u8]) -> std::io::Result<usize> {
+                Err(std::io::ErrorKind::Other.into())
+            }
+        }
+        let file = File::open(testdata("tick_spans.json")).unwrap();
+        let window = FrameWindow {
+            skip: 0,
+            limit: Some(1),
+        };
+        let mut grouper = FrameGrouper::new("tick", window);
+        let reader = BufReader::new(file.chain(Unreadable));
+        read_stream(reader, "tick_spans.json", 1, 1, |trace| grouper.push(trace)).unwrap();
+        assert_eq!(grouper.frames.len(), 1);
+    }
+
+    #[test]
+    fn test_frame_window() {
+        let path = testdata("tick_spans.json");
+        let window = FrameWindow {
+            skip: 1,
+            limit: Some(1),
+        };
+        let (all, _) = read_trace_file(&path, "tick", FrameWindow::default(), 1).unwrap();
+        let (frames, _) = read_trace_file(&path, "tick", window, 1).unwrap();
+        assert_eq!(frames.len(), 1);
+        assert_eq!(frames[0].trace.span.id, all[1].trace.span.id);
+        assert_eq!(frames[0].child_traces.len(), all[1].child_traces.len());
+
+        let (indexed, _) = index_trace_file(&path, "tick", window).unwrap();
+        assert_eq!(indexed.len(), 1);
+        let children = indexed[0].unread_children[0].read().unwrap();
+        assert_eq!(children.len(), all[1].child_traces.len());
+    }
+
+    #[test]
+    fn test_merge_indexed_frames() {
+        let path = testdata("tick_spans.json");
+        let (indexed, _) = index_trace_file(&path, "tick", FrameWindow::default()).unwrap();
+        let mut merged = merge_frames(&indexed, 2);
+        assert_eq!(merged.len(), 1);
+        assert_eq!(merged[0].merged_ids, [indexed[0].id(0), indexed[1].id(1)]);
+        assert!(merged[0].child_traces.is_empty());
+        merged[0].read_children().unwrap();
+        assert!(merged[0].unread_children.is_empty());
+        assert_eq!(merged[0].child_traces.len(), 3);
+    }
+
+    #[test]
+    fn test_custom_frame_span() {
+        let path = testdata("tick_spans.json");
+        let (frames, _) = read_trace_file(&path, "tick", FrameWindow::default(), 1).unwrap();
+        assert_eq!(frames.len(), 2);
+        assert_eq!(frames[0].trace.span.id, Some(1));
+        assert_eq!(frames[0].child_traces.len(), 1);
+        assert_eq!(frames[1].child_traces.len(), 2);
+
+        // no span is called frame, so there are no frames
+        let (frames, _) =
+            read_trace_file(&path, DEFAULT_FRAME_SPAN, FrameWindow::default(), 1).unwrap();
+        assert!(frames.is_empty());
+    }
+
+    #[test]
+    fn test_index_trace_file() {
+        let path = testdata("nested_spans.json");
+        let (frames, _) =
+            read_trace_file(&path, DEFAULT_FRAME_SPAN, FrameWindow::default(), 1).unwrap();
+        let (indexed, skipped) =
+            index_trace_file(&path, DEFAULT_FRAME_SPAN, FrameWindow::default()).unwrap();
+        assert!(skipped.is_empty());
+        assert_eq!(indexed.len(), frames.len());
+        for (frame, indexed) in frames.iter().zip(&indexed) {
+            assert!(indexed.child_traces.is_empty());
+            let children = indexed.unread_children[0].read().unwrap();
+            let names = |traces: &[Trace]| -> Vec<String> {
+                traces.iter().map(|t| t.span.name.clone()).collect()
+            };
+            assert_eq!(names(&children), names(&frame.child_traces));
+        }
+    }
+
+    #[test]
+    fn test_span_tree() {
+        let (frames, _) = read_trace_file(
+            &testdata("nested_spans.json"),
+            DEFAULT_FRAME_SPAN,
+            FrameWindow::default(),
+            1,
+        )
+        .unwrap();
+        let order: Vec<usize> = (0..frames[0].child_traces.len()).collect();
+        let tree = frames[0].span_tree(&order);
+        let names = |nodes: &[SpanNode]| -> Vec<String> {
+            nodes.iter().map(|n| n.trace.span.name.clone()).collect()
+        };
+        assert_eq!(names(&tree), ["draw", "input"]);
+        assert_eq!(names(&tree[0].children), ["walls", "sprites"]);
+        assert!(tree[1].children.is_empty());
+    }
 }